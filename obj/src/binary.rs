@@ -0,0 +1,543 @@
+//! A compact binary format for object meshes that loads without parsing.
+//!
+//! The format is little-endian throughout. It starts with a 32 byte header:
+//!
+//! | Offset | Size | Contents                                        |
+//! |--------|------|-------------------------------------------------|
+//! | 0      | 4    | The magic bytes `MOBJ`                          |
+//! | 4      | 2    | The format version, currently `1`               |
+//! | 6      | 2    | The attribute mask (see `ATTRIBUTE_*`)          |
+//! | 8      | 4    | The number of points                            |
+//! | 12     | 4    | The number of texture coordinates               |
+//! | 16     | 4    | The number of normal vectors                    |
+//! | 20     | 4    | The number of vertex colors                     |
+//! | 24     | 8    | Reserved, zero                                  |
+//!
+//! The header is followed by the raw `f32` buffers for the points, texture
+//! coordinates, normals, and vertex colors, in that order, for each attribute
+//! in the mask. Every attribute in the mask has one element per point.
+//! Texture coordinates and normals left out of the mask read as zeros, while
+//! a mesh without colors in the mask has no vertex colors.
+//! Every buffer starts on a 16 byte boundary, so a `MeshView` can borrow the
+//! buffers straight out of a suitably aligned byte slice.
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::mem;
+use std::path::Path;
+use std::slice;
+
+use crate::ObjMesh;
+
+
+/// The magic bytes at the start of every binary mesh file.
+pub const MAGIC: [u8; 4] = *b"MOBJ";
+
+/// The version of the binary mesh format written by this crate.
+pub const VERSION: u16 = 1;
+
+/// The attribute mask bit for the points buffer.
+pub const ATTRIBUTE_POINTS: u16 = 0x0001;
+
+/// The attribute mask bit for the texture coordinates buffer.
+pub const ATTRIBUTE_TEX_COORDS: u16 = 0x0002;
+
+/// The attribute mask bit for the normal vector buffer.
+pub const ATTRIBUTE_NORMALS: u16 = 0x0004;
+
+/// The attribute mask bit for the vertex color buffer.
+pub const ATTRIBUTE_COLORS: u16 = 0x0008;
+
+const HEADER_LEN: usize = 32;
+const BUFFER_ALIGNMENT: usize = 16;
+
+
+#[inline]
+fn align_up(offset: usize) -> usize {
+    (offset + BUFFER_ALIGNMENT - 1) & !(BUFFER_ALIGNMENT - 1)
+}
+
+#[inline]
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+#[inline]
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+/// The location of each attribute buffer in a binary mesh file, as byte
+/// ranges paired with element counts.
+struct Layout {
+    points: (usize, usize),
+    tex_coords: (usize, usize),
+    normals: (usize, usize),
+    colors: Option<(usize, usize)>,
+}
+
+/// Validate the header of a binary mesh file and locate its buffers.
+fn parse_header(bytes: &[u8]) -> Result<Layout, String> {
+    if bytes.len() < HEADER_LEN {
+        return Err(format!("ERROR: binary mesh is too short: {} bytes", bytes.len()));
+    }
+    if bytes[0..4] != MAGIC {
+        return Err(String::from("ERROR: not a binary mesh file"));
+    }
+
+    let version = read_u16(bytes, 4);
+    if version != VERSION {
+        return Err(format!("ERROR: unsupported binary mesh version {}", version));
+    }
+
+    let mask = read_u16(bytes, 6);
+    let known = ATTRIBUTE_POINTS | ATTRIBUTE_TEX_COORDS | ATTRIBUTE_NORMALS | ATTRIBUTE_COLORS;
+    if mask & !known != 0 {
+        return Err(format!("ERROR: unknown binary mesh attributes in mask {:#06x}", mask));
+    }
+
+    let attributes = [
+        (ATTRIBUTE_POINTS, read_u32(bytes, 8) as usize, 3),
+        (ATTRIBUTE_TEX_COORDS, read_u32(bytes, 12) as usize, 2),
+        (ATTRIBUTE_NORMALS, read_u32(bytes, 16) as usize, 3),
+        (ATTRIBUTE_COLORS, read_u32(bytes, 20) as usize, 3),
+    ];
+    let mut ranges = [(0, 0); 4];
+    let mut offset = HEADER_LEN;
+    for (i, (bit, count, components)) in attributes.iter().enumerate() {
+        if mask & bit == 0 {
+            if *count != 0 {
+                return Err(format!("ERROR: binary mesh attribute {:#06x} has elements but is not in the mask", bit));
+            }
+            continue;
+        }
+
+        let start = align_up(offset);
+        let end = count
+            .checked_mul(components * mem::size_of::<f32>())
+            .and_then(|len| len.checked_add(start))
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| String::from("ERROR: binary mesh is truncated"))?;
+        if *count != attributes[0].1 {
+            return Err(format!(
+                "ERROR: binary mesh attribute {:#06x} has {} elements but the mesh has {} points",
+                bit, count, attributes[0].1
+            ));
+        }
+        ranges[i] = (start, *count);
+        offset = end;
+    }
+
+    Ok(Layout {
+        points: ranges[0],
+        tex_coords: ranges[1],
+        normals: ranges[2],
+        colors: if mask & ATTRIBUTE_COLORS != 0 { Some(ranges[3]) } else { None },
+    })
+}
+
+/// A mesh whose buffers are borrowed directly from the bytes of a binary
+/// mesh file, without copying. The buffers of attributes missing from the
+/// file are empty, and a file without vertex colors has none.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MeshView<'a> {
+    points: &'a [[f32; 3]],
+    tex_coords: &'a [[f32; 2]],
+    normals: &'a [[f32; 3]],
+    colors: Option<&'a [[f32; 3]]>,
+}
+
+impl<'a> MeshView<'a> {
+    /// Borrow the buffers of a binary mesh file. The slice must start on a
+    /// four byte boundary, as it does when the file is read into memory
+    /// allocated for `f32` or `u32` values, or when the file is memory mapped.
+    /// Use `load_from_memory` to read a file from an arbitrary slice instead.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<MeshView<'a>, String> {
+        if cfg!(target_endian = "big") {
+            return Err(String::from("ERROR: binary meshes can only be borrowed on little-endian targets"));
+        }
+        let misalignment = bytes.as_ptr() as usize % mem::align_of::<f32>();
+        if misalignment != 0 {
+            return Err(String::from("ERROR: binary mesh buffer is not aligned for f32 data"));
+        }
+
+        let layout = parse_header(bytes)?;
+        // SAFETY: `parse_header` checked that every range lies inside `bytes`,
+        // the ranges start on 16 byte boundaries relative to a four byte
+        // aligned pointer, and any bit pattern is a valid `f32`.
+        let (points, tex_coords, normals, colors) = unsafe {
+            (
+                slice::from_raw_parts(bytes.as_ptr().add(layout.points.0) as *const [f32; 3], layout.points.1),
+                slice::from_raw_parts(bytes.as_ptr().add(layout.tex_coords.0) as *const [f32; 2], layout.tex_coords.1),
+                slice::from_raw_parts(bytes.as_ptr().add(layout.normals.0) as *const [f32; 3], layout.normals.1),
+                layout.colors.map(|(start, count)| {
+                    slice::from_raw_parts(bytes.as_ptr().add(start) as *const [f32; 3], count)
+                }),
+            )
+        };
+
+        Ok(MeshView { points, tex_coords, normals, colors })
+    }
+
+    /// Get the points buffer.
+    #[inline]
+    pub fn points(&self) -> &'a [[f32; 3]] {
+        self.points
+    }
+
+    /// Get the texture coordinates buffer.
+    #[inline]
+    pub fn tex_coords(&self) -> &'a [[f32; 2]] {
+        self.tex_coords
+    }
+
+    /// Get the normal vector buffer.
+    #[inline]
+    pub fn normals(&self) -> &'a [[f32; 3]] {
+        self.normals
+    }
+
+    /// Get the vertex color buffer, if the file has vertex colors.
+    #[inline]
+    pub fn colors(&self) -> Option<&'a [[f32; 3]]> {
+        self.colors
+    }
+
+    /// Get the number of vertices in the mesh.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Determine whether the mesh has no vertices.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Copy the borrowed buffers into an owned mesh, filling in missing
+    /// texture coordinates and normals with zeros.
+    pub fn to_obj_mesh(&self) -> ObjMesh {
+        let len = self.points.len();
+        let mesh = ObjMesh::new(
+            self.points.to_vec(),
+            fill_missing(self.tex_coords.to_vec(), len),
+            fill_missing(self.normals.to_vec(), len),
+        );
+
+        match self.colors {
+            Some(colors) => mesh.with_colors(colors.to_vec()).unwrap(),
+            None => mesh,
+        }
+    }
+}
+
+fn read_buffer<const N: usize>(bytes: &[u8], (start, count): (usize, usize)) -> Vec<[f32; N]> {
+    let data = &bytes[start..(start + count * N * mem::size_of::<f32>())];
+    data.chunks_exact(N * mem::size_of::<f32>()).map(|chunk| {
+        let mut element = [0_f32; N];
+        for (i, component) in element.iter_mut().enumerate() {
+            let offset = i * mem::size_of::<f32>();
+            *component = f32::from_le_bytes([chunk[offset], chunk[offset + 1], chunk[offset + 2], chunk[offset + 3]]);
+        }
+        element
+    }).collect()
+}
+
+/// Replace the empty buffer of an attribute missing from a binary mesh file
+/// with zeros.
+fn fill_missing<const N: usize>(buffer: Vec<[f32; N]>, len: usize) -> Vec<[f32; N]> {
+    if buffer.is_empty() { vec![[0_f32; N]; len] } else { buffer }
+}
+
+/// Read a binary mesh file from a buffer with any alignment, copying its
+/// buffers into a new mesh. Missing texture coordinates and normals are
+/// filled in with zeros.
+pub fn load_from_memory(bytes: &[u8]) -> Result<ObjMesh, String> {
+    let layout = parse_header(bytes)?;
+    let len = layout.points.1;
+    let mesh = ObjMesh::try_new(
+        read_buffer(bytes, layout.points),
+        fill_missing(read_buffer(bytes, layout.tex_coords), len),
+        fill_missing(read_buffer(bytes, layout.normals), len),
+    );
+    let mesh = match layout.colors {
+        Some(colors) => mesh.and_then(|mesh| mesh.with_colors(read_buffer(bytes, colors))),
+        None => mesh,
+    };
+
+    mesh.map_err(|e| format!("ERROR: {}", e))
+}
+
+/// Read a binary mesh file from a reader.
+pub fn load<R: io::Read>(reader: &mut R) -> Result<ObjMesh, String> {
+    let mut buffer = vec![];
+    reader.read_to_end(&mut buffer).map_err(|e| format!("ERROR: {}", e))?;
+
+    load_from_memory(&buffer)
+}
+
+/// Load a binary mesh file from disk.
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<ObjMesh, String> {
+    let file = match File::open(path.as_ref()) {
+        Ok(handle) => handle,
+        Err(_) => {
+            return Err(format!("ERROR: file not found: {}", path.as_ref().display()));
+        }
+    };
+
+    let mut reader = BufReader::new(file);
+    load(&mut reader)
+}
+
+/// Write a mesh in the binary mesh format. Texture coordinates and normals
+/// that are all zero are left out of the attribute mask, and so are the
+/// vertex colors of a mesh without them.
+pub fn write<W: io::Write>(writer: &mut W, mesh: &ObjMesh) -> io::Result<()> {
+    let present = |data: &[f32]| if data.iter().all(|x| *x == 0_f32) { 0 } else { mesh.len() };
+    let tex_coords = mesh.tex_coords().concat();
+    let normals = mesh.normals().concat();
+    let colors = mesh.colors().map_or((0, vec![]), |colors| (mesh.len(), colors.concat()));
+    let buffers: [(u16, usize, Vec<f32>); 4] = [
+        (ATTRIBUTE_POINTS, mesh.len(), mesh.points().concat()),
+        (ATTRIBUTE_TEX_COORDS, present(&tex_coords), tex_coords),
+        (ATTRIBUTE_NORMALS, present(&normals), normals),
+        (ATTRIBUTE_COLORS, colors.0, colors.1),
+    ];
+
+    let mut header = [0_u8; HEADER_LEN];
+    header[0..4].copy_from_slice(&MAGIC);
+    header[4..6].copy_from_slice(&VERSION.to_le_bytes());
+    let mut mask = 0_u16;
+    for (i, (bit, count, _)) in buffers.iter().enumerate() {
+        if *count > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("mesh buffer is too large for the binary mesh format: {} elements", count),
+            ));
+        }
+        if *count > 0 {
+            mask |= bit;
+        }
+        let offset = 8 + 4 * i;
+        header[offset..(offset + 4)].copy_from_slice(&(*count as u32).to_le_bytes());
+    }
+    header[6..8].copy_from_slice(&mask.to_le_bytes());
+    writer.write_all(&header)?;
+
+    let padding = [0_u8; BUFFER_ALIGNMENT];
+    let mut offset = HEADER_LEN;
+    for (_, count, data) in buffers.iter() {
+        if *count == 0 {
+            continue;
+        }
+
+        let start = align_up(offset);
+        writer.write_all(&padding[..(start - offset)])?;
+        let mut bytes = Vec::with_capacity(data.len() * mem::size_of::<f32>());
+        for value in data.iter() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        writer.write_all(&bytes)?;
+        offset = start + bytes.len();
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod binary_tests {
+    use std::fmt::Write;
+    use std::hint::black_box;
+    use std::time::Instant;
+
+    use super::MeshView;
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+        ];
+        let tex_coords = vec![
+            [0.0, 0.0], [1.0, 0.0], [1.0, 1.0],
+            [0.0, 0.0], [1.0, 1.0], [0.0, 1.0],
+        ];
+        let normals = vec![[0.0, 0.0, 1.0]; 6];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    /// Copy bytes into storage that is aligned for `f32` data.
+    fn aligned(bytes: &[u8]) -> Vec<f32> {
+        let mut storage = vec![0_f32; bytes.len() / 4 + 1];
+        for (i, chunk) in bytes.chunks(4).enumerate() {
+            let mut word = [0_u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            storage[i] = f32::from_ne_bytes(word);
+        }
+
+        storage
+    }
+
+    fn as_bytes(storage: &[f32]) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(storage.as_ptr() as *const u8, 4 * storage.len()) }
+    }
+
+    #[test]
+    fn test_binary_mesh_view_borrows_buffers() {
+        let expected = test_mesh();
+        let mut bytes = vec![];
+        super::write(&mut bytes, &expected).unwrap();
+        let storage = aligned(&bytes);
+        let view = MeshView::from_bytes(as_bytes(&storage)).unwrap();
+
+        assert_eq!(view.points(), expected.points());
+        assert_eq!(view.tex_coords(), expected.tex_coords());
+        assert_eq!(view.normals(), expected.normals());
+        assert_eq!(view.to_obj_mesh(), expected);
+    }
+
+    #[test]
+    fn test_binary_mesh_buffers_are_aligned() {
+        let mut bytes = vec![];
+        super::write(&mut bytes, &test_mesh()).unwrap();
+        let storage = aligned(&bytes);
+        let view = MeshView::from_bytes(as_bytes(&storage)).unwrap();
+        let base = storage.as_ptr() as usize;
+
+        assert_eq!((view.points().as_ptr() as usize - base) % 16, 0);
+        assert_eq!((view.tex_coords().as_ptr() as usize - base) % 16, 0);
+        assert_eq!((view.normals().as_ptr() as usize - base) % 16, 0);
+    }
+
+    #[test]
+    fn test_binary_mesh_round_trip_from_unaligned_memory() {
+        let expected = test_mesh();
+        let mut bytes = vec![0xFF];
+        super::write(&mut bytes, &expected).unwrap();
+        let storage = aligned(&bytes);
+        let unaligned = &as_bytes(&storage)[1..];
+
+        assert!(MeshView::from_bytes(unaligned).is_err());
+        assert_eq!(super::load_from_memory(unaligned).unwrap(), expected);
+    }
+
+    /// Build the OBJ text of an `n` by `n` grid of quads with texture
+    /// coordinates and normals.
+    fn grid_obj(n: usize) -> String {
+        let mut obj = String::new();
+        for i in 0..=n {
+            for j in 0..=n {
+                writeln!(obj, "v {} {} 0", i, j).unwrap();
+                writeln!(obj, "vt {} {}", i as f32 / n as f32, j as f32 / n as f32).unwrap();
+            }
+        }
+        obj.push_str("vn 0 0 1\n");
+        let vertex = |i: usize, j: usize| i * (n + 1) + j + 1;
+        for i in 0..n {
+            for j in 0..n {
+                let [a, b, c, d] = [vertex(i, j), vertex(i + 1, j), vertex(i + 1, j + 1), vertex(i, j + 1)];
+                writeln!(obj, "f {}/{}/1 {}/{}/1 {}/{}/1 {}/{}/1", a, a, b, b, c, c, d, d).unwrap();
+            }
+        }
+
+        obj
+    }
+
+    /// Compare opening a mesh in the binary format against parsing the same
+    /// mesh as OBJ text. Borrowing the buffers only reads the header, so it
+    /// beats the parser by far more than the factor checked here, in debug
+    /// builds and on busy machines too.
+    #[test]
+    fn test_mesh_view_is_an_order_of_magnitude_faster_than_obj() {
+        let obj = grid_obj(60);
+        let start = Instant::now();
+        let mesh = crate::load_from_memory(obj.as_bytes()).unwrap();
+        let obj_time = start.elapsed();
+
+        let mut bytes = vec![];
+        super::write(&mut bytes, &mesh).unwrap();
+        let storage = aligned(&bytes);
+        let iterations = 100;
+        let start = Instant::now();
+        for _ in 0..iterations {
+            let view = MeshView::from_bytes(black_box(as_bytes(&storage))).unwrap();
+            black_box(view.points());
+        }
+        let view_time = start.elapsed() / iterations;
+
+        assert_eq!(super::load_from_memory(as_bytes(&storage)).unwrap(), mesh);
+        assert!(10 * view_time < obj_time, "MeshView took {:?} against {:?} for OBJ", view_time, obj_time);
+    }
+
+    #[test]
+    fn test_binary_mesh_without_tex_coords() {
+        let mesh = ObjMesh::new(vec![[1.0, 2.0, 3.0]; 3], vec![[0.0, 0.0]; 3], vec![[0.0, 1.0, 0.0]; 3]);
+        let mut bytes = vec![];
+        super::write(&mut bytes, &mesh).unwrap();
+        let storage = aligned(&bytes);
+        let view = MeshView::from_bytes(as_bytes(&storage)).unwrap();
+
+        assert_eq!(bytes[6], (super::ATTRIBUTE_POINTS | super::ATTRIBUTE_NORMALS) as u8);
+        assert!(view.tex_coords().is_empty());
+        assert_eq!(view.to_obj_mesh(), mesh);
+        assert_eq!(super::load_from_memory(&bytes).unwrap(), mesh);
+    }
+
+    #[test]
+    fn test_binary_mesh_with_colors_round_trip() {
+        let colors = vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 0.0, 0.0], [0.5, 0.2, 0.8]];
+        let expected = test_mesh().with_colors(colors).unwrap();
+        let mut bytes = vec![];
+        super::write(&mut bytes, &expected).unwrap();
+        let storage = aligned(&bytes);
+        let view = MeshView::from_bytes(as_bytes(&storage)).unwrap();
+
+        assert_eq!(bytes[6] as u16 & super::ATTRIBUTE_COLORS, super::ATTRIBUTE_COLORS);
+        assert_eq!(view.colors(), expected.colors());
+        assert_eq!((view.colors().unwrap().as_ptr() as usize - storage.as_ptr() as usize) % 16, 0);
+        assert_eq!(view.to_obj_mesh(), expected);
+        assert_eq!(super::load_from_memory(&bytes).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_binary_mesh_without_colors_has_none() {
+        let mut bytes = vec![];
+        super::write(&mut bytes, &test_mesh()).unwrap();
+        let storage = aligned(&bytes);
+        let view = MeshView::from_bytes(as_bytes(&storage)).unwrap();
+
+        assert_eq!(bytes[6] as u16 & super::ATTRIBUTE_COLORS, 0);
+        assert_eq!(view.colors(), None);
+        assert_eq!(super::load_from_memory(&bytes).unwrap().colors(), None);
+    }
+
+    #[test]
+    fn test_binary_mesh_with_mismatched_counts_is_rejected() {
+        let mut bytes = vec![];
+        super::write(&mut bytes, &test_mesh()).unwrap();
+        bytes[12..16].copy_from_slice(&5_u32.to_le_bytes());
+
+        assert!(super::load_from_memory(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_truncated_binary_mesh_is_rejected() {
+        let mut bytes = vec![];
+        super::write(&mut bytes, &test_mesh()).unwrap();
+        bytes.truncate(bytes.len() - 4);
+
+        assert!(super::load_from_memory(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_binary_mesh_with_wrong_magic_is_rejected() {
+        let mut bytes = vec![];
+        super::write(&mut bytes, &test_mesh()).unwrap();
+        bytes[0] = b'X';
+
+        assert!(super::load_from_memory(&bytes).is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! Bounding volumes of object meshes.
+use crate::math;
+use crate::ObjMesh;
+
+
+/// An axis-aligned bounding box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    /// Get the center of the box.
+    #[inline]
+    pub fn center(&self) -> [f32; 3] {
+        math::scale(math::add(self.min, self.max), 0.5)
+    }
+
+    /// Get the length of the box along each axis.
+    #[inline]
+    pub fn size(&self) -> [f32; 3] {
+        math::sub(self.max, self.min)
+    }
+
+    /// Determine whether a point lies inside or on the boundary of the box.
+    #[inline]
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        (0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+}
+
+/// A bounding sphere.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: [f32; 3],
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Determine whether a point lies inside or on the boundary of the sphere.
+    #[inline]
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        math::length(math::sub(point, self.center)) <= self.radius
+    }
+}
+
+/// An oriented bounding box. The box spans `half_extents[i]` in either
+/// direction from `center` along each of the orthonormal `axes`, which form a
+/// right-handed basis.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrientedBox {
+    pub center: [f32; 3],
+    pub axes: [[f32; 3]; 3],
+    pub half_extents: [f32; 3],
+}
+
+impl OrientedBox {
+    /// Determine whether a point lies inside or on the boundary of the box,
+    /// allowing for a small amount of rounding error.
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        let offset = math::sub(point, self.center);
+        (0..3).all(|i| {
+            let tolerance = 1e-5 * f32::max(1_f32, self.half_extents[i]);
+            math::dot(offset, self.axes[i]).abs() <= self.half_extents[i] + tolerance
+        })
+    }
+}
+
+/// Compute the eigenvalues and eigenvectors of a symmetric 3x3 matrix with
+/// the cyclic Jacobi method. The eigenvectors are the columns of the returned
+/// matrix.
+fn symmetric_eigen(mut a: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..50 {
+        let off_diagonal = a[0][1] * a[0][1] + a[0][2] * a[0][2] + a[1][2] * a[1][2];
+        if off_diagonal < 1e-30 {
+            break;
+        }
+
+        for &(p, q) in [(0, 1), (0, 2), (1, 2)].iter() {
+            if a[p][q].abs() < 1e-30 {
+                continue;
+            }
+
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let t = if theta == 0.0 { 1.0 } else { t };
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            for row in a.iter_mut() {
+                let akp = row[p];
+                let akq = row[q];
+                row[p] = c * akp - s * akq;
+                row[q] = s * akp + c * akq;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            for (k, (apk, aqk)) in row_p.iter().zip(row_q.iter()).enumerate() {
+                a[p][k] = c * apk - s * aqk;
+                a[q][k] = s * apk + c * aqk;
+            }
+            for row in v.iter_mut() {
+                let vkp = row[p];
+                let vkq = row[q];
+                row[p] = c * vkp - s * vkq;
+                row[q] = s * vkp + c * vkq;
+            }
+        }
+    }
+
+    let vectors = [
+        [v[0][0], v[1][0], v[2][0]],
+        [v[0][1], v[1][1], v[2][1]],
+        [v[0][2], v[1][2], v[2][2]],
+    ];
+
+    ([a[0][0], a[1][1], a[2][2]], vectors)
+}
+
+impl ObjMesh {
+    /// Compute the axis-aligned bounding box of the mesh, or `None` when the
+    /// mesh has no points.
+    pub fn aabb(&self) -> Option<Aabb> {
+        let first = *self.points().first()?;
+        let mut aabb = Aabb { min: first, max: first };
+        for point in self.points().iter() {
+            for (i, value) in point.iter().enumerate() {
+                aabb.min[i] = f32::min(aabb.min[i], *value);
+                aabb.max[i] = f32::max(aabb.max[i], *value);
+            }
+        }
+
+        Some(aabb)
+    }
+
+    /// Compute a bounding sphere of the mesh with Ritter's algorithm, or
+    /// `None` when the mesh has no points. The sphere is usually within a few
+    /// percent of the smallest enclosing sphere.
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        let points = self.points();
+        let first = *points.first()?;
+        let farthest_from = |origin: [f32; 3]| {
+            let mut farthest = origin;
+            let mut distance = 0_f32;
+            for point in points.iter() {
+                let d = math::length(math::sub(*point, origin));
+                if d > distance {
+                    distance = d;
+                    farthest = *point;
+                }
+            }
+            farthest
+        };
+
+        let a = farthest_from(first);
+        let b = farthest_from(a);
+        let mut center = math::scale(math::add(a, b), 0.5);
+        let mut radius = 0.5 * math::length(math::sub(b, a));
+        for point in points.iter() {
+            let distance = math::length(math::sub(*point, center));
+            if distance > radius {
+                let new_radius = 0.5 * (radius + distance);
+                let shift = (new_radius - radius) / distance;
+                center = math::add(center, math::scale(math::sub(*point, center), shift));
+                radius = new_radius;
+            }
+        }
+
+        // Widen the sphere slightly so that every point tests as contained
+        // despite rounding in the incremental updates.
+        for point in points.iter() {
+            radius = f32::max(radius, math::length(math::sub(*point, center)));
+        }
+
+        Some(BoundingSphere { center, radius })
+    }
+
+    /// Compute an oriented bounding box of the mesh whose axes are the
+    /// principal axes of its points, or `None` when the mesh has no points.
+    pub fn oriented_bounding_box(&self) -> Option<OrientedBox> {
+        let points = self.points();
+        if points.is_empty() {
+            return None;
+        }
+
+        let count = points.len() as f64;
+        let mut mean = [0_f64; 3];
+        for point in points.iter() {
+            for i in 0..3 {
+                mean[i] += point[i] as f64 / count;
+            }
+        }
+        let mut covariance = [[0_f64; 3]; 3];
+        for point in points.iter() {
+            let d = [point[0] as f64 - mean[0], point[1] as f64 - mean[1], point[2] as f64 - mean[2]];
+            for i in 0..3 {
+                for j in 0..3 {
+                    covariance[i][j] += d[i] * d[j] / count;
+                }
+            }
+        }
+
+        let (_, vectors) = symmetric_eigen(covariance);
+        let to_f32 = |v: [f64; 3]| [v[0] as f32, v[1] as f32, v[2] as f32];
+        let x_axis = math::normalize(to_f32(vectors[0]));
+        let y_axis = math::normalize(math::sub(
+            to_f32(vectors[1]), math::scale(x_axis, math::dot(x_axis, to_f32(vectors[1])))
+        ));
+        let axes = [x_axis, y_axis, math::cross(x_axis, y_axis)];
+
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for point in points.iter() {
+            for i in 0..3 {
+                let projection = math::dot(*point, axes[i]);
+                min[i] = f32::min(min[i], projection);
+                max[i] = f32::max(max[i], projection);
+            }
+        }
+
+        let mut center = [0_f32; 3];
+        let mut half_extents = [0_f32; 3];
+        for i in 0..3 {
+            center = math::add(center, math::scale(axes[i], 0.5 * (min[i] + max[i])));
+            half_extents[i] = 0.5 * (max[i] - min[i]);
+        }
+
+        Some(OrientedBox { center, axes, half_extents })
+    }
+}
+
+
+#[cfg(test)]
+mod bounds_tests {
+    use super::Aabb;
+    use crate::math;
+    use crate::ObjMesh;
+
+    fn box_mesh(size: [f32; 3]) -> ObjMesh {
+        let mut points = vec![];
+        for i in 0..8 {
+            let corner = [
+                if i & 1 == 0 { 0.0 } else { size[0] },
+                if i & 2 == 0 { 0.0 } else { size[1] },
+                if i & 4 == 0 { 0.0 } else { size[2] },
+            ];
+            points.push(corner);
+        }
+        let len = points.len();
+
+        ObjMesh::new(points, vec![[0.0, 0.0]; len], vec![[0.0, 0.0, 0.0]; len])
+    }
+
+    #[test]
+    fn test_aabb() {
+        let mut mesh = box_mesh([1.0, 2.0, 3.0]);
+        mesh.translate([-1.0, 0.0, 1.0]);
+        let result = mesh.aabb().unwrap();
+        let expected = Aabb { min: [-1.0, 0.0, 1.0], max: [0.0, 2.0, 4.0] };
+
+        assert_eq!(result, expected);
+        assert_eq!(result.center(), [-0.5, 1.0, 2.5]);
+        assert_eq!(result.size(), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_empty_mesh_has_no_bounds() {
+        let mesh = ObjMesh::new(vec![], vec![], vec![]);
+
+        assert!(mesh.aabb().is_none());
+        assert!(mesh.bounding_sphere().is_none());
+        assert!(mesh.oriented_bounding_box().is_none());
+    }
+
+    #[test]
+    fn test_bounding_sphere_contains_points() {
+        let mesh = box_mesh([2.0, 2.0, 2.0]);
+        let sphere = mesh.bounding_sphere().unwrap();
+
+        for point in mesh.points().iter() {
+            assert!(sphere.contains(*point));
+        }
+        // The smallest enclosing sphere of the cube has radius sqrt(3).
+        assert!(sphere.radius < 1.1 * 3_f32.sqrt());
+    }
+
+    #[test]
+    fn test_oriented_bounding_box_of_rotated_box() {
+        let mut mesh = box_mesh([4.0, 2.0, 1.0]);
+        mesh.translate([-2.0, -1.0, -0.5]);
+        mesh.rotate([1.0, 2.0, 3.0], 0.8);
+        mesh.translate([5.0, 0.0, -5.0]);
+        let obb = mesh.oriented_bounding_box().unwrap();
+        let mut half_extents = obb.half_extents;
+        half_extents.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        for point in mesh.points().iter() {
+            assert!(obb.contains(*point));
+        }
+        for (result, expected) in half_extents.iter().zip([0.5, 1.0, 2.0].iter()) {
+            assert!((result - expected).abs() < 1e-4, "half extents {:?}", obb.half_extents);
+        }
+        assert!(math::length(math::sub(obb.center, [5.0, 0.0, -5.0])) < 1e-4);
+        assert!((math::dot(math::cross(obb.axes[0], obb.axes[1]), obb.axes[2]) - 1.0).abs() < 1e-5);
+    }
+}
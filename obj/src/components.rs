@@ -0,0 +1,111 @@
+//! Connected components of object meshes.
+//!
+//! Two triangles are connected when they share a vertex position, up to a
+//! distance tolerance. Scanned and kitbashed models often hold many separate
+//! islands in one object; splitting them apart makes it easy to drop floating
+//! debris or to break a prop into its pieces.
+use crate::spatial;
+use crate::union_find::UnionFind;
+use crate::ObjMesh;
+
+
+impl ObjMesh {
+    /// Label every triangle with the connected component it belongs to,
+    /// treating vertices within `epsilon` of each other as shared. Returns the
+    /// component id of each triangle along with the number of components.
+    /// Components are numbered in the order of their first triangles.
+    pub fn triangle_components(&self, epsilon: f32) -> (Vec<usize>, usize) {
+        let triangle_count = self.points().len() / 3;
+        let classes = spatial::position_classes(&self.points()[..(3 * triangle_count)], epsilon);
+
+        // Union each triangle with the first triangle to use each position.
+        let mut sets = UnionFind::new(triangle_count);
+        let mut owners = vec![usize::MAX; classes.len()];
+        for (vertex, class) in classes.iter().enumerate() {
+            let triangle = vertex / 3;
+            if owners[*class] == usize::MAX {
+                owners[*class] = triangle;
+            } else {
+                sets.union(owners[*class], triangle);
+            }
+        }
+
+        let mut labels = vec![usize::MAX; triangle_count];
+        let mut components = Vec::with_capacity(triangle_count);
+        let mut count = 0;
+        for triangle in 0..triangle_count {
+            let root = sets.find(triangle);
+            if labels[root] == usize::MAX {
+                labels[root] = count;
+                count += 1;
+            }
+            components.push(labels[root]);
+        }
+
+        (components, count)
+    }
+
+    /// Split the mesh into one mesh per connected component, treating
+    /// vertices within `epsilon` of each other as shared. The meshes are
+    /// ordered as in `triangle_components`.
+    pub fn split_components(&self, epsilon: f32) -> Vec<ObjMesh> {
+        let (components, count) = self.triangle_components(epsilon);
+        let mut meshes = vec![ObjMesh::default(); count];
+        for (triangle, component) in components.iter().enumerate() {
+            let mesh = &mut meshes[*component];
+            for vertex in (3 * triangle)..(3 * triangle + 3) {
+                mesh.push_vertex_from(self, vertex);
+            }
+        }
+
+        meshes
+    }
+}
+
+
+#[cfg(test)]
+mod components_tests {
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![
+            // A quad made of two triangles sharing an edge.
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+            // A separate triangle far away.
+            [5.0, 5.0, 5.0], [6.0, 5.0, 5.0], [5.0, 6.0, 5.0],
+            // A triangle touching the quad up to a small gap.
+            [1.0, 1.0, 0.001], [2.0, 1.0, 0.0], [2.0, 2.0, 0.0],
+        ];
+        let len = points.len();
+
+        ObjMesh::new(points, vec![[0.0, 0.0]; len], vec![[0.0, 0.0, 1.0]; len])
+    }
+
+    #[test]
+    fn test_triangle_components() {
+        let mesh = test_mesh();
+
+        assert_eq!(mesh.triangle_components(0.0), (vec![0, 0, 1, 2], 3));
+        assert_eq!(mesh.triangle_components(0.01), (vec![0, 0, 1, 0], 2));
+    }
+
+    #[test]
+    fn test_split_components() {
+        let mesh = test_mesh();
+        let parts = mesh.split_components(0.01);
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].len(), 9);
+        assert_eq!(parts[1].points(), &mesh.points()[6..9]);
+        assert_eq!(parts[1].normals().len(), 3);
+    }
+
+    #[test]
+    fn test_components_of_empty_mesh() {
+        let mesh = ObjMesh::new(vec![], vec![], vec![]);
+
+        assert_eq!(mesh.triangle_components(0.0), (vec![], 0));
+        assert!(mesh.split_components(0.0).is_empty());
+    }
+}
@@ -0,0 +1,83 @@
+//! Conversions between object meshes and the vector types of math crates.
+//!
+//! The conversions go through the `mint` interoperability types, so they work
+//! with any math crate that supports `mint`, such as `glam`, `nalgebra`, and
+//! `cgmath`, without tying this crate to any one of them. Positions convert
+//! through `mint::Point3`, normals through `mint::Vector3`, and texture
+//! coordinates through `mint::Vector2`.
+use crate::{ObjMesh, ObjMeshError};
+
+
+impl ObjMesh {
+    /// Construct a mesh from slices of any vector types that convert into
+    /// `mint` vectors, or return an error when the slices have different
+    /// lengths.
+    pub fn from_mint<P, T, N>(points: &[P], tex_coords: &[T], normals: &[N]) -> Result<ObjMesh, ObjMeshError>
+    where
+        P: Copy + Into<mint::Point3<f32>>,
+        T: Copy + Into<mint::Vector2<f32>>,
+        N: Copy + Into<mint::Vector3<f32>>,
+    {
+        let points = points.iter().map(|point| (*point).into().into()).collect();
+        let tex_coords = tex_coords.iter().map(|tex_coord| (*tex_coord).into().into()).collect();
+        let normals = normals.iter().map(|normal| (*normal).into().into()).collect();
+
+        ObjMesh::try_new(points, tex_coords, normals)
+    }
+
+    /// Iterate over the points of the mesh as any point type that converts
+    /// from `mint::Point3`.
+    pub fn points_as<V: From<mint::Point3<f32>>>(&self) -> impl Iterator<Item = V> + '_ {
+        self.points().iter().map(|point| V::from(mint::Point3::from(*point)))
+    }
+
+    /// Iterate over the texture coordinates of the mesh as any vector type
+    /// that converts from `mint::Vector2`.
+    pub fn tex_coords_as<V: From<mint::Vector2<f32>>>(&self) -> impl Iterator<Item = V> + '_ {
+        self.tex_coords().iter().map(|tex_coord| V::from(mint::Vector2::from(*tex_coord)))
+    }
+
+    /// Iterate over the normal vectors of the mesh as any vector type that
+    /// converts from `mint::Vector3`.
+    pub fn normals_as<V: From<mint::Vector3<f32>>>(&self) -> impl Iterator<Item = V> + '_ {
+        self.normals().iter().map(|normal| V::from(mint::Vector3::from(*normal)))
+    }
+}
+
+
+#[cfg(test)]
+mod convert_tests {
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]];
+        let tex_coords = vec![[0.1, 0.2], [0.3, 0.4], [0.5, 0.6]];
+        let normals = vec![[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    #[test]
+    fn test_mesh_from_mint_vectors() {
+        let expected = test_mesh();
+        let points: Vec<mint::Point3<f32>> = expected.points().iter().map(|p| (*p).into()).collect();
+        let tex_coords: Vec<mint::Vector2<f32>> = expected.tex_coords().iter().map(|t| (*t).into()).collect();
+        let normals: Vec<mint::Vector3<f32>> = expected.normals().iter().map(|n| (*n).into()).collect();
+        let result = ObjMesh::from_mint(&points, &tex_coords, &normals).unwrap();
+
+        assert_eq!(result, expected);
+        assert!(ObjMesh::from_mint(&points, &tex_coords[1..], &normals).is_err());
+    }
+
+    #[test]
+    fn test_mesh_to_mint_vectors() {
+        let mesh = test_mesh();
+        let points: Vec<mint::Point3<f32>> = mesh.points_as().collect();
+        let tex_coords: Vec<mint::Vector2<f32>> = mesh.tex_coords_as().collect();
+        let normals: Vec<mint::Vector3<f32>> = mesh.normals_as().collect();
+
+        assert_eq!(points[1], mint::Point3 { x: 4.0, y: 5.0, z: 6.0 });
+        assert_eq!(tex_coords[2], mint::Vector2 { x: 0.5, y: 0.6 });
+        assert_eq!(normals[0], mint::Vector3 { x: 0.0, y: 0.0, z: 1.0 });
+    }
+}
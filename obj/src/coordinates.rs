@@ -0,0 +1,203 @@
+//! Conversions between the coordinate systems used by modeling tools, file
+//! formats, and engines.
+//!
+//! A coordinate system is described by the axis that points up, the axis
+//! that points forward out of the front of a model, and its handedness. The
+//! remaining axis points to the model's right. In a right-handed system it is
+//! `forward x up`, and in a left-handed system it is `up x forward`.
+use crate::math;
+use crate::transform;
+use crate::ObjMesh;
+
+
+/// A signed coordinate axis.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl Axis {
+    /// Get the unit vector pointing along the axis.
+    #[inline]
+    pub fn vector(self) -> [f32; 3] {
+        match self {
+            Axis::PositiveX => [1.0, 0.0, 0.0],
+            Axis::NegativeX => [-1.0, 0.0, 0.0],
+            Axis::PositiveY => [0.0, 1.0, 0.0],
+            Axis::NegativeY => [0.0, -1.0, 0.0],
+            Axis::PositiveZ => [0.0, 0.0, 1.0],
+            Axis::NegativeZ => [0.0, 0.0, -1.0],
+        }
+    }
+}
+
+/// The handedness of a coordinate system.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Handedness {
+    RightHanded,
+    LeftHanded,
+}
+
+/// A coordinate system given by its up axis, forward axis, and handedness.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CoordinateSystem {
+    up: Axis,
+    forward: Axis,
+    handedness: Handedness,
+}
+
+impl CoordinateSystem {
+    /// The coordinate system of glTF: Y up, Z forward, right-handed.
+    pub const GLTF: CoordinateSystem = CoordinateSystem {
+        up: Axis::PositiveY,
+        forward: Axis::PositiveZ,
+        handedness: Handedness::RightHanded,
+    };
+
+    /// The coordinate system of Blender: Z up, -Y forward, right-handed.
+    pub const BLENDER: CoordinateSystem = CoordinateSystem {
+        up: Axis::PositiveZ,
+        forward: Axis::NegativeY,
+        handedness: Handedness::RightHanded,
+    };
+
+    /// The coordinate system of Unity and Direct3D style engines: Y up,
+    /// Z forward, left-handed.
+    pub const Y_UP_LEFT_HANDED: CoordinateSystem = CoordinateSystem {
+        up: Axis::PositiveY,
+        forward: Axis::PositiveZ,
+        handedness: Handedness::LeftHanded,
+    };
+
+    /// Construct a coordinate system, or return `None` when the up and
+    /// forward axes are not perpendicular.
+    pub fn new(up: Axis, forward: Axis, handedness: Handedness) -> Option<CoordinateSystem> {
+        if math::dot(up.vector(), forward.vector()) != 0_f32 {
+            return None;
+        }
+
+        Some(CoordinateSystem { up, forward, handedness })
+    }
+
+    /// Get the axis pointing up.
+    #[inline]
+    pub fn up(&self) -> Axis {
+        self.up
+    }
+
+    /// Get the axis pointing forward.
+    #[inline]
+    pub fn forward(&self) -> Axis {
+        self.forward
+    }
+
+    /// Get the handedness of the coordinate system.
+    #[inline]
+    pub fn handedness(&self) -> Handedness {
+        self.handedness
+    }
+
+    /// Get the vector pointing to the right.
+    pub fn right(&self) -> [f32; 3] {
+        let (up, forward) = (self.up.vector(), self.forward.vector());
+        match self.handedness {
+            Handedness::RightHanded => math::cross(forward, up),
+            Handedness::LeftHanded => math::cross(up, forward),
+        }
+    }
+
+    /// Construct the matrix that converts coordinates in this system into
+    /// coordinates in `target`. It maps the right, up, and forward directions
+    /// of one system onto those of the other.
+    pub fn conversion(&self, target: &CoordinateSystem) -> [[f32; 4]; 4] {
+        let source = [self.right(), self.up.vector(), self.forward.vector()];
+        let target = [target.right(), target.up.vector(), target.forward.vector()];
+
+        // The source basis is orthonormal, so its inverse is its transpose,
+        // and the conversion is the sum of the outer products of the matching
+        // target and source directions.
+        let mut matrix = transform::IDENTITY;
+        for (column, entries) in matrix.iter_mut().take(3).enumerate() {
+            for (row, entry) in entries.iter_mut().take(3).enumerate() {
+                *entry = (0..3).map(|k| target[k][row] * source[k][column]).sum();
+            }
+        }
+
+        matrix
+    }
+}
+
+impl ObjMesh {
+    /// Convert the mesh from one coordinate system into another, returning the
+    /// applied transform. Points and normals are converted together, and the
+    /// winding of every triangle is reversed when the handedness changes so
+    /// that front faces stay front faces.
+    pub fn convert_coordinates(&mut self, from: &CoordinateSystem, to: &CoordinateSystem) -> [[f32; 4]; 4] {
+        let matrix = from.conversion(to);
+        self.transform(&matrix);
+
+        matrix
+    }
+}
+
+
+#[cfg(test)]
+mod coordinates_tests {
+    use super::{Axis, CoordinateSystem, Handedness};
+    use crate::math;
+    use crate::transform;
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 2.0, 3.0]];
+        let tex_coords = vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+        let normal = math::normalize([0.0, -3.0, 2.0]);
+
+        ObjMesh::new(points, tex_coords, vec![normal; 3])
+    }
+
+    #[test]
+    fn test_blender_to_gltf() {
+        let mut mesh = test_mesh();
+        mesh.convert_coordinates(&CoordinateSystem::BLENDER, &CoordinateSystem::GLTF);
+
+        // Blender's glTF exporter maps (x, y, z) to (x, z, -y).
+        assert_eq!(mesh.points(), &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 3.0, -2.0]]);
+        assert_eq!(mesh.normals(), &[math::normalize([0.0, 2.0, 3.0]); 3]);
+        assert_eq!(mesh.tex_coords(), test_mesh().tex_coords());
+    }
+
+    #[test]
+    fn test_handedness_change_flips_winding() {
+        let mut mesh = test_mesh();
+        mesh.convert_coordinates(&CoordinateSystem::GLTF, &CoordinateSystem::Y_UP_LEFT_HANDED);
+
+        assert_eq!(mesh.points(), &[[0.0, 0.0, 0.0], [0.0, 2.0, 3.0], [-1.0, 0.0, 0.0]]);
+        assert_eq!(mesh.tex_coords(), &[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0]]);
+        let normal = mesh.normals()[0];
+        let face_normal = math::triangle_normal(mesh.points()[0], mesh.points()[1], mesh.points()[2]);
+        assert!(math::dot(normal, face_normal) > 0.0);
+    }
+
+    #[test]
+    fn test_conversion_round_trip() {
+        let from = CoordinateSystem::new(Axis::NegativeX, Axis::PositiveZ, Handedness::LeftHanded).unwrap();
+        let to = CoordinateSystem::BLENDER;
+        let product = transform::multiply(&to.conversion(&from), &from.conversion(&to));
+
+        assert_eq!(product, transform::IDENTITY);
+        assert_eq!(from.conversion(&from), transform::IDENTITY);
+    }
+
+    #[test]
+    fn test_parallel_axes_are_rejected() {
+        let result = CoordinateSystem::new(Axis::PositiveY, Axis::NegativeY, Handedness::RightHanded);
+
+        assert!(result.is_none());
+    }
+}
@@ -0,0 +1,789 @@
+//! Loading and saving object meshes as glTF 2.0 assets.
+//!
+//! Loading reads the static triangle geometry of every mesh in an asset: the
+//! positions, the normals, texture coordinate set 0, and color set 0 of each
+//! triangle primitive. Node transforms, skins, morph targets, and animations
+//! are ignored, so each mesh comes out in its own model space. An `ObjMesh`
+//! has no materials, so materials are not read at all, and vertex colors are
+//! loaded as stored, without the base color factor of their material. A mesh
+//! gets vertex colors when one of its primitives has colors, and the vertices
+//! of its other primitives are white. Buffers are read by the `gltf` crate,
+//! but external buffers must lie inside the directory of the asset.
+//!
+//! When saving, a mesh is written as a single indexed triangle primitive. Vertices that
+//! agree exactly in every attribute are merged when building the index
+//! buffer. The normal and texture coordinate attributes are left out when the
+//! mesh does not carry them, which is the case when every value in the
+//! channel is zero, and vertex colors are written as `COLOR_0` when the mesh
+//! has them. glTF requires unit normals, so normals are normalized, and a
+//! vertex with a zero normal takes the normal of its triangle, or `+z` when
+//! the triangle is degenerate and has none. No material is written, since an `ObjMesh` carries
+//! none, so viewers draw the primitive with the glTF default material.
+//!
+//! glTF puts the texture coordinate origin at the top left corner of an image
+//! while OBJ puts it at the bottom left, so the `v` coordinate is flipped on
+//! the way in and on the way out.
+//!
+//! The module is only built with the `gltf` feature, since the `gltf` crate
+//! brings image decoders along with it.
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Component, Path};
+
+use ::gltf::buffer;
+use ::gltf::buffer::Source;
+use ::gltf::json;
+use ::gltf::json::mesh::Semantic;
+use ::gltf::json::validation::Checked::Valid;
+use ::gltf::json::validation::USize64;
+use ::gltf::mesh::Mode;
+use ::gltf::{Document, Gltf};
+
+use crate::math;
+use crate::ObjMesh;
+
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+
+/// An accessor together with the buffer view that backs it.
+struct Accessor {
+    byte_offset: usize,
+    byte_length: usize,
+    target: json::buffer::Target,
+    component_type: json::accessor::ComponentType,
+    count: usize,
+    accessor_type: json::accessor::Type,
+    bounds: Option<(Vec<f32>, Vec<f32>)>,
+}
+
+/// The binary payload and JSON description of a mesh.
+struct GltfAsset {
+    root: json::Root,
+    bin: Vec<u8>,
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn push_f32s(bin: &mut Vec<u8>, values: &[f32]) {
+    for value in values.iter() {
+        bin.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn pad_to_four(bin: &mut Vec<u8>, fill: u8) {
+    let padding = (4 - bin.len() % 4) % 4;
+    bin.resize(bin.len() + padding, fill);
+}
+
+fn bounds<const N: usize>(values: &[[f32; N]]) -> (Vec<f32>, Vec<f32>) {
+    let mut min = vec![f32::INFINITY; N];
+    let mut max = vec![f32::NEG_INFINITY; N];
+    for value in values.iter() {
+        for i in 0..N {
+            min[i] = f32::min(min[i], value[i]);
+            max[i] = f32::max(max[i], value[i]);
+        }
+    }
+
+    (min, max)
+}
+
+/// Percent-encode a relative file name so that it is a valid URI reference.
+fn encode_uri(uri: &str) -> String {
+    let mut encoded = String::with_capacity(uri.len());
+    for byte in uri.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char);
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+/// Decode the percent-encoded characters in a URI reference.
+fn decode_uri(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[(i + 1)..(i + 3)]).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Check that the URI of an external buffer is a relative path that stays
+/// inside the directory of the asset, so that an asset cannot read files
+/// elsewhere on the system. Absolute paths, URI schemes other than `data:`,
+/// and paths that climb out of the directory with `..` are rejected.
+fn check_buffer_uri(uri: &str) -> Result<(), String> {
+    let path = decode_uri(uri);
+    let mut depth = 0_usize;
+    let escapes = path.contains(':') || Path::new(&path).components().any(|component| match component {
+        Component::Normal(_) => {
+            depth += 1;
+            false
+        }
+        Component::CurDir => false,
+        Component::ParentDir => match depth.checked_sub(1) {
+            Some(parent) => {
+                depth = parent;
+                false
+            }
+            None => true,
+        },
+        Component::RootDir | Component::Prefix(_) => true,
+    });
+
+    match escapes {
+        true => Err(format!("ERROR: glTF buffer `{}` is outside the directory of the asset", uri)),
+        false => Ok(()),
+    }
+}
+
+/// Gather the contents of every buffer in a glTF asset. External buffers are
+/// resolved relative to `base`, and are an error when there is no `base`.
+fn load_buffers(document: &Document, base: Option<&Path>, blob: Option<Vec<u8>>) -> Result<Vec<buffer::Data>, String> {
+    for buffer in document.buffers() {
+        match buffer.source() {
+            Source::Uri(uri) if !uri.starts_with("data:") => {
+                check_buffer_uri(uri)?;
+                if base.is_none() {
+                    return Err(format!("ERROR: cannot resolve external glTF buffer `{}` without a file path", uri));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    ::gltf::import_buffers(document, base, blob).map_err(|e| format!("ERROR: {}", e))
+}
+
+/// Expand the vertex indices of a primitive into a triangle list.
+fn triangle_list(mode: Mode, indices: &[u32]) -> Vec<u32> {
+    let mut triangles = vec![];
+    match mode {
+        Mode::Triangles => {
+            let whole = indices.len() - indices.len() % 3;
+            triangles.extend_from_slice(&indices[..whole]);
+        }
+        Mode::TriangleStrip => {
+            for i in 2..indices.len() {
+                if i % 2 == 0 {
+                    triangles.extend_from_slice(&[indices[i - 2], indices[i - 1], indices[i]]);
+                } else {
+                    triangles.extend_from_slice(&[indices[i - 1], indices[i - 2], indices[i]]);
+                }
+            }
+        }
+        Mode::TriangleFan => {
+            for i in 2..indices.len() {
+                triangles.extend_from_slice(&[indices[0], indices[i - 1], indices[i]]);
+            }
+        }
+        _ => {}
+    }
+
+    triangles
+}
+
+fn load_meshes(gltf: Gltf, base: Option<&Path>) -> Result<Vec<ObjMesh>, String> {
+    let Gltf { document, blob } = gltf;
+    let buffers = load_buffers(&document, base, blob)?;
+    let mut meshes = vec![];
+    for mesh in document.meshes() {
+        let mut points = vec![];
+        let mut tex_coords = vec![];
+        let mut normals = vec![];
+        let mut colors = vec![];
+        let mut has_colors = false;
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+            let primitive_points: Vec<[f32; 3]> = match reader.read_positions() {
+                Some(positions) => positions.collect(),
+                None => continue,
+            };
+            let primitive_normals: Vec<[f32; 3]> = match reader.read_normals() {
+                Some(normals) => normals.collect(),
+                None => vec![[0_f32; 3]; primitive_points.len()],
+            };
+            let primitive_tex_coords: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+                Some(tex_coords) => tex_coords.into_f32().map(|[u, v]| [u, 1_f32 - v]).collect(),
+                None => vec![[0_f32; 2]; primitive_points.len()],
+            };
+            let primitive_colors: Option<Vec<[f32; 3]>> = reader.read_colors(0).map(|colors| {
+                colors.into_rgb_f32().collect()
+            });
+            has_colors |= primitive_colors.is_some();
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..(primitive_points.len() as u32)).collect(),
+            };
+
+            for index in triangle_list(primitive.mode(), &indices).iter() {
+                let index = *index as usize;
+                if index >= primitive_points.len() {
+                    return Err(format!("ERROR: glTF primitive refers to missing vertex {}", index));
+                }
+                points.push(primitive_points[index]);
+                normals.push(primitive_normals.get(index).copied().unwrap_or([0_f32; 3]));
+                tex_coords.push(primitive_tex_coords.get(index).copied().unwrap_or([0_f32; 2]));
+                let color = primitive_colors.as_ref().and_then(|colors| colors.get(index)).copied();
+                colors.push(color.unwrap_or([1_f32; 3]));
+            }
+        }
+        let mesh = ObjMesh::try_new(points, tex_coords, normals);
+        let mesh = match has_colors {
+            true => mesh.and_then(|mesh| mesh.with_colors(colors)),
+            false => mesh,
+        };
+        meshes.push(mesh.map_err(|e| format!("ERROR: {}", e))?);
+    }
+
+    Ok(meshes)
+}
+
+/// Parse a `.gltf` or `.glb` asset from a buffer, returning one mesh per glTF
+/// mesh. Buffers must be embedded in the GLB binary chunk or in data URIs,
+/// since there is no file path to resolve external buffers against.
+pub fn load_from_memory(buffer: &[u8]) -> Result<Vec<ObjMesh>, String> {
+    let gltf = Gltf::from_slice(buffer).map_err(|e| format!("ERROR: {}", e))?;
+    load_meshes(gltf, None)
+}
+
+/// Parse a `.gltf` or `.glb` asset from a reader, returning one mesh per glTF
+/// mesh. External buffers are not supported; use `load_file` for those.
+pub fn load<R: io::Read>(reader: &mut R) -> Result<Vec<ObjMesh>, String> {
+    let mut buffer = vec![];
+    reader.read_to_end(&mut buffer).map_err(|e| format!("ERROR: {}", e))?;
+
+    load_from_memory(&buffer)
+}
+
+/// Load a `.gltf` or `.glb` asset from disk, returning one mesh per glTF
+/// mesh. External buffers are resolved relative to the asset's directory.
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Vec<ObjMesh>, String> {
+    let file = match File::open(path.as_ref()) {
+        Ok(handle) => handle,
+        Err(_) => {
+            return Err(format!("ERROR: file not found: {}", path.as_ref().display()));
+        }
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut buffer = vec![];
+    io::Read::read_to_end(&mut reader, &mut buffer).map_err(|e| format!("ERROR: {}", e))?;
+    let gltf = Gltf::from_slice(&buffer).map_err(|e| format!("ERROR: {}", e))?;
+    let base = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+
+    load_meshes(gltf, Some(base))
+}
+
+/// Append a buffer view over `bin` for an accessor, and the accessor itself.
+fn push_accessor(root: &mut json::Root, accessor: Accessor) -> json::Index<json::Accessor> {
+    let view = root.push(json::buffer::View {
+        buffer: json::Index::new(0),
+        byte_length: USize64::from(accessor.byte_length),
+        byte_offset: Some(USize64::from(accessor.byte_offset)),
+        byte_stride: None,
+        name: None,
+        target: Some(Valid(accessor.target)),
+        extensions: None,
+        extras: Default::default(),
+    });
+    let (min, max) = match accessor.bounds {
+        Some((min, max)) => (Some(json::Value::from(min)), Some(json::Value::from(max))),
+        None => (None, None),
+    };
+
+    root.push(json::Accessor {
+        buffer_view: Some(view),
+        byte_offset: None,
+        count: USize64::from(accessor.count),
+        component_type: Valid(json::accessor::GenericComponentType(accessor.component_type)),
+        extensions: None,
+        extras: Default::default(),
+        type_: Valid(accessor.accessor_type),
+        min,
+        max,
+        name: None,
+        normalized: false,
+        sparse: None,
+    })
+}
+
+/// Append the values of a vertex attribute to `bin` and describe them with
+/// an accessor.
+fn push_attribute<const N: usize>(
+    root: &mut json::Root,
+    bin: &mut Vec<u8>,
+    values: &[[f32; N]],
+    accessor_type: json::accessor::Type,
+    bounds: Option<(Vec<f32>, Vec<f32>)>,
+) -> json::Index<json::Accessor> {
+    let byte_offset = bin.len();
+    push_f32s(bin, values.concat().as_slice());
+
+    push_accessor(root, Accessor {
+        byte_offset,
+        byte_length: bin.len() - byte_offset,
+        target: json::buffer::Target::ArrayBuffer,
+        component_type: json::accessor::ComponentType::F32,
+        count: values.len(),
+        accessor_type,
+        bounds,
+    })
+}
+
+/// Find a unit normal for every vertex of a mesh. Vertices whose normal is
+/// zero or not finite take the normal of their triangle instead, since glTF
+/// requires unit normals, and the vertices of a degenerate triangle, which
+/// has no normal, point along `+z`. Returns `None` when the mesh has no
+/// normals.
+fn unit_normals(mesh: &ObjMesh) -> Option<Vec<[f32; 3]>> {
+    let normals = mesh.normals();
+    if normals.iter().all(|normal| *normal == [0_f32; 3]) {
+        return None;
+    }
+
+    let points = mesh.points();
+    let unit_normals = (0..mesh.len()).map(|i| {
+        let length = math::length(normals[i]);
+        if length.is_finite() && length > 0_f32 {
+            return math::scale(normals[i], 1_f32 / length);
+        }
+
+        let triangle = 3 * (i / 3);
+        let normal = math::triangle_normal(points[triangle], points[triangle + 1], points[triangle + 2]);
+        match normal.iter().all(|value| value.is_finite()) && normal != [0_f32; 3] {
+            true => normal,
+            false => [0_f32, 0_f32, 1_f32],
+        }
+    }).collect();
+
+    Some(unit_normals)
+}
+
+/// Build the JSON document and binary buffer for a mesh. When `bin_uri` is
+/// `None` the buffer is left without a URI, as a GLB file requires.
+fn build_asset(mesh: &ObjMesh, bin_uri: Option<&str>) -> io::Result<GltfAsset> {
+    let remainder = mesh.len() % 3;
+    if mesh.is_empty() || remainder != 0 {
+        return Err(invalid_input(format!(
+            "mesh has {} vertices, which is not a positive whole number of triangles", mesh.len()
+        )));
+    }
+    if mesh.points().iter().flatten().any(|value| !value.is_finite()) {
+        return Err(invalid_input(String::from("mesh has positions that are not finite")));
+    }
+
+    let points = mesh.points();
+    let tex_coords = mesh.tex_coords();
+    let normals = unit_normals(mesh);
+    let colors = mesh.colors();
+    let has_tex_coords = tex_coords.iter().any(|tex_coord| *tex_coord != [0_f32, 0_f32]);
+
+    let mut unique_points = vec![];
+    let mut unique_normals = vec![];
+    let mut unique_tex_coords = vec![];
+    let mut unique_colors = vec![];
+    let mut indices = Vec::with_capacity(points.len());
+    let mut index_of = HashMap::new();
+    for i in 0..points.len() {
+        let point = points[i];
+        let normal = normals.as_ref().map_or([0_f32; 3], |normals| normals[i]);
+        let tex_coord = if has_tex_coords {
+            [tex_coords[i][0], 1_f32 - tex_coords[i][1]]
+        } else {
+            [0_f32; 2]
+        };
+        let color = colors.map_or([0_f32; 3], |colors| colors[i]);
+        let key = [
+            point[0].to_bits(), point[1].to_bits(), point[2].to_bits(),
+            normal[0].to_bits(), normal[1].to_bits(), normal[2].to_bits(),
+            tex_coord[0].to_bits(), tex_coord[1].to_bits(),
+            color[0].to_bits(), color[1].to_bits(), color[2].to_bits(),
+        ];
+        let index = *index_of.entry(key).or_insert_with(|| {
+            unique_points.push(point);
+            unique_normals.push(normal);
+            unique_tex_coords.push(tex_coord);
+            unique_colors.push(color);
+            unique_points.len() - 1
+        });
+        indices.push(index);
+    }
+
+    let mut root = json::Root::default();
+    root.asset.generator = Some(String::from("mini_obj"));
+    let mut bin = vec![];
+    let mut attributes = BTreeMap::new();
+    let bounds = Some(bounds(&unique_points));
+    let positions = push_attribute(&mut root, &mut bin, &unique_points, json::accessor::Type::Vec3, bounds);
+    attributes.insert(Valid(Semantic::Positions), positions);
+    if normals.is_some() {
+        let normals = push_attribute(&mut root, &mut bin, &unique_normals, json::accessor::Type::Vec3, None);
+        attributes.insert(Valid(Semantic::Normals), normals);
+    }
+    if has_tex_coords {
+        let tex_coords = push_attribute(&mut root, &mut bin, &unique_tex_coords, json::accessor::Type::Vec2, None);
+        attributes.insert(Valid(Semantic::TexCoords(0)), tex_coords);
+    }
+    if colors.is_some() {
+        let colors = push_attribute(&mut root, &mut bin, &unique_colors, json::accessor::Type::Vec3, None);
+        attributes.insert(Valid(Semantic::Colors(0)), colors);
+    }
+
+    let byte_offset = bin.len();
+    let component_type = if unique_points.len() <= u16::MAX as usize {
+        for index in indices.iter() {
+            bin.extend_from_slice(&(*index as u16).to_le_bytes());
+        }
+        json::accessor::ComponentType::U16
+    } else {
+        for index in indices.iter() {
+            bin.extend_from_slice(&(*index as u32).to_le_bytes());
+        }
+        json::accessor::ComponentType::U32
+    };
+    let indices = push_accessor(&mut root, Accessor {
+        byte_offset,
+        byte_length: bin.len() - byte_offset,
+        target: json::buffer::Target::ElementArrayBuffer,
+        component_type,
+        count: indices.len(),
+        accessor_type: json::accessor::Type::Scalar,
+        bounds: None,
+    });
+    pad_to_four(&mut bin, 0);
+
+    root.push(json::Buffer {
+        byte_length: USize64::from(bin.len()),
+        name: None,
+        uri: bin_uri.map(encode_uri),
+        extensions: None,
+        extras: Default::default(),
+    });
+    let mesh = root.push(json::Mesh {
+        extensions: None,
+        extras: Default::default(),
+        name: None,
+        primitives: vec![json::mesh::Primitive {
+            attributes,
+            extensions: None,
+            extras: Default::default(),
+            indices: Some(indices),
+            material: None,
+            mode: Valid(json::mesh::Mode::Triangles),
+            targets: None,
+        }],
+        weights: None,
+    });
+    let node = root.push(json::Node { mesh: Some(mesh), ..Default::default() });
+    let scene = root.push(json::Scene {
+        extensions: None,
+        extras: Default::default(),
+        name: None,
+        nodes: vec![node],
+    });
+    root.scene = Some(scene);
+
+    Ok(GltfAsset { root, bin })
+}
+
+/// Write a mesh as a glTF JSON document to `json` and its binary buffer to
+/// `bin`. The document refers to the buffer by `bin_uri`, which is usually
+/// the file name of the buffer relative to the document.
+pub fn write_gltf<W: io::Write, B: io::Write>(
+    json: &mut W, bin: &mut B, mesh: &ObjMesh, bin_uri: &str) -> io::Result<()> {
+
+    let asset = build_asset(mesh, Some(bin_uri))?;
+    asset.root.to_writer(&mut *json).map_err(io::Error::from)?;
+    bin.write_all(&asset.bin)
+}
+
+/// Write a mesh as a single binary glTF (GLB) file.
+pub fn write_glb<W: io::Write>(writer: &mut W, mesh: &ObjMesh) -> io::Result<()> {
+    let asset = build_asset(mesh, None)?;
+    let mut json = asset.root.to_vec().map_err(io::Error::from)?;
+    pad_to_four(&mut json, b' ');
+    let total_length = 12 + 8 + json.len() + 8 + asset.bin.len();
+    if total_length > u32::MAX as usize {
+        return Err(invalid_input(format!("mesh is too large for a GLB file: {} bytes", total_length)));
+    }
+
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&GLB_VERSION.to_le_bytes())?;
+    writer.write_all(&(total_length as u32).to_le_bytes())?;
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_CHUNK_JSON.to_le_bytes())?;
+    writer.write_all(&json)?;
+    writer.write_all(&(asset.bin.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_CHUNK_BIN.to_le_bytes())?;
+    writer.write_all(&asset.bin)
+}
+
+/// Save a mesh as a `.gltf` document at `path`, with its binary buffer in a
+/// `.bin` file of the same name next to it.
+pub fn write_gltf_file<P: AsRef<Path>>(path: P, mesh: &ObjMesh) -> io::Result<()> {
+    let path = path.as_ref();
+    let bin_path = path.with_extension("bin");
+    let bin_uri = bin_path.file_name().and_then(|name| name.to_str()).ok_or_else(|| {
+        invalid_input(format!("invalid glTF file name: {}", path.display()))
+    })?;
+
+    let mut json = BufWriter::new(File::create(path)?);
+    let mut bin = BufWriter::new(File::create(&bin_path)?);
+    write_gltf(&mut json, &mut bin, mesh, bin_uri)?;
+    json.flush()?;
+    bin.flush()
+}
+
+/// Save a mesh as a `.glb` file at `path`.
+pub fn write_glb_file<P: AsRef<Path>>(path: P, mesh: &ObjMesh) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_glb(&mut writer, mesh)?;
+    writer.flush()
+}
+
+
+#[cfg(test)]
+mod gltf_tests {
+    use ::gltf::json::mesh::Semantic;
+    use ::gltf::json::validation::Checked;
+    use ::gltf::json::Root;
+
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![
+            [0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [2.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0], [2.0, 1.0, 0.0], [0.0, 1.0, -3.0],
+        ];
+        let tex_coords = vec![
+            [0.0, 0.0], [1.0, 0.0], [1.0, 1.0],
+            [0.0, 0.0], [1.0, 1.0], [0.0, 1.0],
+        ];
+        let normals = vec![[0.0, 0.0, 1.0]; 6];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+    }
+
+    fn write_json(mesh: &ObjMesh) -> (Root, Vec<u8>) {
+        let mut json = vec![];
+        let mut bin = vec![];
+        super::write_gltf(&mut json, &mut bin, mesh, "test mesh.bin").unwrap();
+
+        (Root::from_slice(&json).unwrap(), bin)
+    }
+
+    fn semantics(root: &Root) -> Vec<Semantic> {
+        root.meshes[0].primitives[0].attributes.keys().map(|semantic| match semantic {
+            Checked::Valid(semantic) => semantic.clone(),
+            Checked::Invalid => panic!("invalid attribute semantic"),
+        }).collect()
+    }
+
+    #[test]
+    fn test_gltf_shared_vertices_are_indexed() {
+        let (root, bin) = write_json(&test_mesh());
+        let positions = &root.accessors[0];
+        let indices = &root.accessors[3];
+
+        // Four unique vertices of 8 floats each, plus six 16-bit indices.
+        assert_eq!(bin.len(), 4 * (3 + 3 + 2) * 4 + 6 * 2);
+        assert_eq!(root.buffers[0].uri.as_deref(), Some("test%20mesh.bin"));
+        assert_eq!(positions.count.0, 4);
+        assert_eq!(positions.min, Some(serde_json::json!([0.0, 0.0, -3.0])));
+        assert_eq!(positions.max, Some(serde_json::json!([2.0, 1.0, 0.0])));
+        assert_eq!(indices.count.0, 6);
+        assert_eq!(root.meshes[0].primitives[0].indices.map(|index| index.value()), Some(3));
+        assert!(root.materials.is_empty());
+    }
+
+    #[test]
+    fn test_gltf_missing_channels_are_omitted() {
+        let points = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let mesh = ObjMesh::new(points, vec![[0.0, 0.0]; 3], vec![[0.0, 0.0, 0.0]; 3]);
+        let (root, _) = write_json(&mesh);
+
+        assert_eq!(semantics(&root), vec![Semantic::Positions]);
+    }
+
+    #[test]
+    fn test_gltf_zero_normals_take_the_triangle_normal() {
+        let mut mesh = test_mesh();
+        mesh.push_triangle([[0.0, 0.0, 5.0], [0.0, 2.0, 5.0], [2.0, 0.0, 5.0]], [[0.0, 0.0]; 3], [[0.0, 0.0, 0.0]; 3]);
+        let mut glb = vec![];
+        super::write_glb(&mut glb, &mesh).unwrap();
+        let result = super::load_from_memory(&glb).unwrap();
+
+        assert_eq!(&result[0].normals()[6..], &[[0.0, 0.0, -1.0]; 3]);
+        assert_eq!(&result[0].normals()[..6], &[[0.0, 0.0, 1.0]; 6]);
+    }
+
+    #[test]
+    fn test_gltf_degenerate_triangles_take_a_fallback_normal() {
+        let mut mesh = test_mesh();
+        mesh.push_triangle([[0.0, 0.0, 5.0]; 3], [[0.0, 0.0]; 3], [[0.0, 0.0, 0.0]; 3]);
+        mesh.push_triangle([[0.0, 0.0, 5.0]; 3], [[0.0, 0.0]; 3], [[0.0, 2.0, 0.0]; 3]);
+        let (root, _) = write_json(&mesh);
+        let normals = super::unit_normals(&mesh).unwrap();
+
+        assert!(semantics(&root).contains(&Semantic::Normals));
+        assert_eq!(&normals[..6], test_mesh().normals());
+        assert_eq!(&normals[6..9], &[[0.0, 0.0, 1.0]; 3]);
+        assert_eq!(&normals[9..], &[[0.0, 1.0, 0.0]; 3]);
+    }
+
+    #[test]
+    fn test_gltf_colors_are_written() {
+        let colors = vec![[1.0, 0.0, 0.0]; 6];
+        let (root, bin) = write_json(&test_mesh().with_colors(colors).unwrap());
+
+        assert!(semantics(&root).contains(&Semantic::Colors(0)));
+        assert_eq!(bin.len(), 4 * (3 + 3 + 2 + 3) * 4 + 6 * 2);
+    }
+
+    #[test]
+    fn test_glb_layout() {
+        let mut glb = vec![];
+        super::write_glb(&mut glb, &test_mesh()).unwrap();
+        let json_length = read_u32(&glb, 12) as usize;
+        let bin_offset = 20 + json_length;
+
+        assert_eq!(read_u32(&glb, 0), super::GLB_MAGIC);
+        assert_eq!(read_u32(&glb, 4), 2);
+        assert_eq!(read_u32(&glb, 8) as usize, glb.len());
+        assert_eq!(read_u32(&glb, 16), super::GLB_CHUNK_JSON);
+        assert_eq!(json_length % 4, 0);
+        assert_eq!(read_u32(&glb, bin_offset + 4), super::GLB_CHUNK_BIN);
+        assert_eq!(bin_offset + 8 + read_u32(&glb, bin_offset) as usize, glb.len());
+    }
+
+    #[test]
+    fn test_glb_round_trip() {
+        let expected = test_mesh();
+        let mut glb = vec![];
+        super::write_glb(&mut glb, &expected).unwrap();
+        let result = super::load_from_memory(&glb).unwrap();
+
+        assert_eq!(result, vec![expected]);
+    }
+
+    #[test]
+    fn test_gltf_with_external_buffer_round_trip() {
+        let dir = std::env::temp_dir().join(format!("mini_obj_gltf_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("round trip.gltf");
+        let expected = test_mesh();
+        super::write_gltf_file(&path, &expected).unwrap();
+        let result = super::load_file(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result.unwrap(), vec![expected]);
+    }
+
+    #[test]
+    fn test_gltf_with_data_uri_and_triangle_strip() {
+        let mut bin = vec![];
+        for value in [0.0_f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0].iter() {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        let data = encode_base64(&bin);
+        let json = format!(
+            concat!(
+                "{{\"asset\":{{\"version\":\"2.0\"}},",
+                "\"meshes\":[{{\"primitives\":[{{\"attributes\":{{\"POSITION\":0}},\"material\":0,\"mode\":5}}]}}],",
+                "\"materials\":[{{\"pbrMetallicRoughness\":{{\"baseColorFactor\":[1,0.5,0.25,1]}}}}],",
+                "\"buffers\":[{{\"byteLength\":48,\"uri\":\"data:application/octet-stream;base64,{}\"}}],",
+                "\"bufferViews\":[{{\"buffer\":0,\"byteLength\":48}}],",
+                "\"accessors\":[{{\"bufferView\":0,\"componentType\":5126,\"count\":4,\"type\":\"VEC3\",",
+                "\"min\":[0,0,0],\"max\":[1,1,0]}}]}}"
+            ),
+            data
+        );
+        let result = super::load_from_memory(json.as_bytes()).unwrap();
+        let expected_points = vec![
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0],
+        ];
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].points(), expected_points.as_slice());
+        assert_eq!(result[0].tex_coords(), &[[0.0, 0.0]; 6]);
+        assert_eq!(result[0].colors(), None);
+    }
+
+    #[test]
+    fn test_glb_with_colors_round_trip() {
+        let colors = vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.5, 0.5, 0.5]];
+        let expected = test_mesh().with_colors(colors).unwrap();
+        let mut glb = vec![];
+        super::write_glb(&mut glb, &expected).unwrap();
+        let result = super::load_from_memory(&glb).unwrap();
+
+        assert_eq!(result, vec![expected]);
+    }
+
+    #[test]
+    fn test_gltf_buffers_outside_the_asset_directory_are_rejected() {
+        for uri in ["../mesh.bin", "a/../../mesh.bin", "%2E%2E/mesh.bin", "/etc/passwd", "file:///etc/passwd"].iter() {
+            assert!(super::check_buffer_uri(uri).is_err(), "buffer `{}` was accepted", uri);
+        }
+        for uri in ["mesh.bin", "./buffers/mesh%20one.bin", "buffers/../mesh.bin"].iter() {
+            assert!(super::check_buffer_uri(uri).is_ok(), "buffer `{}` was rejected", uri);
+        }
+    }
+
+    fn encode_base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut encoded = String::new();
+        for chunk in bytes.chunks(3) {
+            let group = (chunk[0] as u32) << 16
+                | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+                | *chunk.get(2).unwrap_or(&0) as u32;
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    encoded.push(ALPHABET[((group >> (18 - 6 * i)) & 0x3F) as usize] as char);
+                } else {
+                    encoded.push('=');
+                }
+            }
+        }
+
+        encoded
+    }
+
+    #[test]
+    fn test_gltf_rejects_partial_triangles() {
+        let mesh = ObjMesh::new(vec![[0.0, 0.0, 0.0]; 4], vec![[0.0, 0.0]; 4], vec![[0.0, 0.0, 1.0]; 4]);
+        let mut glb = vec![];
+
+        assert!(super::write_glb(&mut glb, &mesh).is_err());
+    }
+}
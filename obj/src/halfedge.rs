@@ -0,0 +1,432 @@
+//! A half-edge representation of object meshes for adjacency queries.
+//!
+//! Each triangle is split into three half-edges that run around it in
+//! winding order. A half-edge knows the vertex it starts from, the next and
+//! previous half-edges of its triangle, and its twin: the half-edge running
+//! the opposite way along the same edge in the neighboring triangle.
+//!
+//! Vertices are welded by position when the structure is built, while the
+//! texture coordinates, normals, and colors stay with the triangle corners, so
+//! converting back to an `ObjMesh` keeps UV seams and hard edges intact.
+use std::collections::HashMap;
+
+use crate::spatial;
+use crate::ObjMesh;
+
+
+/// A directed edge of one triangle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HalfEdge {
+    /// The vertex the half-edge starts from.
+    pub vertex: usize,
+    /// The triangle the half-edge belongs to.
+    pub face: usize,
+    /// The next half-edge around the triangle.
+    pub next: usize,
+    /// The previous half-edge around the triangle.
+    pub prev: usize,
+    /// The half-edge running the opposite way along the same edge, or `None`
+    /// when the edge is on a boundary, is shared by more than two triangles,
+    /// or joins two triangles with opposite windings.
+    pub twin: Option<usize>,
+    /// The texture coordinate of the triangle corner at `vertex`.
+    pub tex_coord: [f32; 2],
+    /// The normal of the triangle corner at `vertex`.
+    pub normal: [f32; 3],
+    /// The color of the triangle corner at `vertex`, when the mesh has
+    /// vertex colors.
+    pub color: Option<[f32; 3]>,
+}
+
+/// A triangle mesh with half-edge connectivity.
+#[derive(Clone, Debug)]
+pub struct HalfEdgeMesh {
+    positions: Vec<[f32; 3]>,
+    half_edges: Vec<HalfEdge>,
+    outgoing: Vec<Vec<usize>>,
+    edges: HashMap<(usize, usize), Vec<usize>>,
+}
+
+/// The key of the undirected edge between two vertices.
+#[inline]
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+impl HalfEdgeMesh {
+    /// Build a half-edge mesh from an object mesh, welding vertices whose
+    /// positions lie within `epsilon` of each other. Triangles that collapse
+    /// onto fewer than three vertices are left out, as are vertices after the
+    /// last whole triangle.
+    pub fn from_obj_mesh(mesh: &ObjMesh, epsilon: f32) -> HalfEdgeMesh {
+        let triangle_count = mesh.len() / 3;
+        let classes = spatial::position_classes(&mesh.points()[..(3 * triangle_count)], epsilon);
+
+        let mut ids = vec![usize::MAX; classes.len()];
+        let mut positions = vec![];
+        for (i, class) in classes.iter().enumerate() {
+            if *class == i {
+                ids[i] = positions.len();
+                positions.push(mesh.points()[i]);
+            }
+        }
+
+        let mut half_edges = vec![];
+        for triangle in 0..triangle_count {
+            let corners = [3 * triangle, 3 * triangle + 1, 3 * triangle + 2];
+            let vertices = [ids[classes[corners[0]]], ids[classes[corners[1]]], ids[classes[corners[2]]]];
+            if vertices[0] == vertices[1] || vertices[1] == vertices[2] || vertices[2] == vertices[0] {
+                continue;
+            }
+
+            let face = half_edges.len() / 3;
+            let base = half_edges.len();
+            for (k, corner) in corners.iter().enumerate() {
+                half_edges.push(HalfEdge {
+                    vertex: vertices[k],
+                    face,
+                    next: base + (k + 1) % 3,
+                    prev: base + (k + 2) % 3,
+                    twin: None,
+                    tex_coord: mesh.tex_coords()[*corner],
+                    normal: mesh.normals()[*corner],
+                    color: mesh.colors().map(|colors| colors[*corner]),
+                });
+            }
+        }
+
+        let mut outgoing = vec![vec![]; positions.len()];
+        let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (h, half_edge) in half_edges.iter().enumerate() {
+            outgoing[half_edge.vertex].push(h);
+            let target = half_edges[half_edge.next].vertex;
+            edges.entry(edge_key(half_edge.vertex, target)).or_default().push(h);
+        }
+        for shared in edges.values() {
+            if let [a, b] = shared[..] {
+                if half_edges[a].vertex != half_edges[b].vertex {
+                    half_edges[a].twin = Some(b);
+                    half_edges[b].twin = Some(a);
+                }
+            }
+        }
+
+        HalfEdgeMesh { positions, half_edges, outgoing, edges }
+    }
+
+    /// Convert the mesh back into an object mesh, one triangle per face.
+    pub fn to_obj_mesh(&self) -> ObjMesh {
+        let mut mesh = ObjMesh::with_capacity(self.half_edges.len());
+        for half_edge in self.half_edges.iter() {
+            let point = self.positions[half_edge.vertex];
+            match half_edge.color {
+                Some(color) => mesh.push_colored_vertex(point, half_edge.tex_coord, half_edge.normal, color),
+                None => mesh.push_vertex(point, half_edge.tex_coord, half_edge.normal),
+            }
+        }
+
+        mesh
+    }
+
+    /// Get the number of distinct vertices.
+    #[inline]
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Get the number of triangles.
+    #[inline]
+    pub fn face_count(&self) -> usize {
+        self.half_edges.len() / 3
+    }
+
+    /// Get the number of distinct undirected edges.
+    #[inline]
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Get the position of a vertex.
+    #[inline]
+    pub fn position(&self, vertex: usize) -> [f32; 3] {
+        self.positions[vertex]
+    }
+
+    /// Get the positions of all the vertices.
+    #[inline]
+    pub fn positions(&self) -> &[[f32; 3]] {
+        &self.positions
+    }
+
+    /// Get all of the half-edges. The half-edges of face `f` are `3 * f`,
+    /// `3 * f + 1`, and `3 * f + 2`.
+    #[inline]
+    pub fn half_edges(&self) -> &[HalfEdge] {
+        &self.half_edges
+    }
+
+    /// Get a half-edge.
+    #[inline]
+    pub fn half_edge(&self, half_edge: usize) -> &HalfEdge {
+        &self.half_edges[half_edge]
+    }
+
+    /// Get the vertex a half-edge points to.
+    #[inline]
+    pub fn target(&self, half_edge: usize) -> usize {
+        self.half_edges[self.half_edges[half_edge].next].vertex
+    }
+
+    /// Get the vertices of a triangle in winding order.
+    pub fn face_vertices(&self, face: usize) -> [usize; 3] {
+        [
+            self.half_edges[3 * face].vertex,
+            self.half_edges[3 * face + 1].vertex,
+            self.half_edges[3 * face + 2].vertex,
+        ]
+    }
+
+    /// Get the half-edges that start from a vertex.
+    #[inline]
+    pub fn outgoing(&self, vertex: usize) -> &[usize] {
+        &self.outgoing[vertex]
+    }
+
+    /// Get the triangles that share the edge between two vertices, in either
+    /// direction. The result is empty when the vertices are not joined by an
+    /// edge.
+    pub fn edge_faces(&self, a: usize, b: usize) -> Vec<usize> {
+        match self.edges.get(&edge_key(a, b)) {
+            Some(shared) => shared.iter().map(|h| self.half_edges[*h].face).collect(),
+            None => vec![],
+        }
+    }
+
+    /// Iterate over the undirected edges of the mesh, each as the pair of
+    /// vertices it joins, smaller first, with the half-edges that run along it.
+    pub fn edges(&self) -> impl Iterator<Item = ((usize, usize), &[usize])> {
+        self.edges.iter().map(|(edge, shared)| (*edge, shared.as_slice()))
+    }
+
+    /// Get the half-edges that lie on the boundary of the mesh, which are the
+    /// half-edges of edges used by only one triangle.
+    pub fn boundary_half_edges(&self) -> Vec<usize> {
+        (0..self.half_edges.len()).filter(|h| {
+            let target = self.target(*h);
+            self.edges[&edge_key(self.half_edges[*h].vertex, target)].len() == 1
+        }).collect()
+    }
+
+    /// Get the edges shared by more than two triangles.
+    pub fn non_manifold_edges(&self) -> Vec<(usize, usize)> {
+        let mut edges: Vec<(usize, usize)> = self.edges.iter()
+            .filter(|(_, shared)| shared.len() > 2)
+            .map(|(edge, _)| *edge)
+            .collect();
+        edges.sort_unstable();
+
+        edges
+    }
+
+    /// Walk the fan of triangles around a vertex through twin links, starting
+    /// from a boundary if there is one. Returns the outgoing half-edges in
+    /// order, along with the last neighbor of an open fan.
+    fn fan(&self, vertex: usize) -> (Vec<usize>, Option<usize>) {
+        let outgoing = &self.outgoing[vertex];
+        let start = match outgoing.iter().find(|h| self.half_edges[**h].twin.is_none()) {
+            Some(h) => *h,
+            None => match outgoing.first() {
+                Some(h) => *h,
+                None => return (vec![], None),
+            },
+        };
+
+        let mut fan = vec![start];
+        let mut current = start;
+        loop {
+            let prev = self.half_edges[current].prev;
+            match self.half_edges[prev].twin {
+                Some(twin) if twin == start => return (fan, None),
+                Some(twin) if fan.len() < outgoing.len() => {
+                    fan.push(twin);
+                    current = twin;
+                }
+                _ => return (fan, Some(self.half_edges[prev].vertex)),
+            }
+        }
+    }
+
+    /// Get the neighbors of a vertex. When the triangles around the vertex
+    /// can be walked through twin links, which needs a manifold vertex with
+    /// a consistent winding, the neighbors are ordered around the vertex in
+    /// the winding direction of its triangles, starting from the boundary if
+    /// the vertex lies on one. Otherwise they are in no particular order.
+    pub fn one_ring(&self, vertex: usize) -> Vec<usize> {
+        let (fan, last) = self.fan(vertex);
+        if fan.len() == self.outgoing[vertex].len() {
+            let mut ring: Vec<usize> = fan.iter().map(|h| self.target(*h)).collect();
+            ring.extend(last);
+            return ring;
+        }
+
+        let mut ring = vec![];
+        for h in self.outgoing[vertex].iter() {
+            for neighbor in [self.target(*h), self.half_edges[self.half_edges[*h].prev].vertex].iter() {
+                if !ring.contains(neighbor) {
+                    ring.push(*neighbor);
+                }
+            }
+        }
+
+        ring
+    }
+
+    /// Determine whether the triangles around a vertex form a single fan,
+    /// joined by edges that leave the vertex and are shared by at most two
+    /// triangles. The edges are followed regardless of winding, so a
+    /// triangle wound the wrong way does not split the fan.
+    fn is_manifold_vertex(&self, vertex: usize) -> bool {
+        let outgoing = &self.outgoing[vertex];
+        let mut reached = vec![false; outgoing.len()];
+        let mut stack = vec![];
+        if !outgoing.is_empty() {
+            reached[0] = true;
+            stack.push(0);
+        }
+        while let Some(i) = stack.pop() {
+            let h = outgoing[i];
+            for neighbor in [self.target(h), self.half_edges[self.half_edges[h].prev].vertex].iter() {
+                let faces = self.edge_faces(vertex, *neighbor);
+                if faces.len() > 2 {
+                    return false;
+                }
+                for face in faces {
+                    if let Some(j) = outgoing.iter().position(|o| self.half_edges[*o].face == face) {
+                        if !reached[j] {
+                            reached[j] = true;
+                            stack.push(j);
+                        }
+                    }
+                }
+            }
+        }
+
+        reached.iter().all(|r| *r)
+    }
+
+    /// Get the vertices whose triangles do not form a single fan, such as the
+    /// shared tip of two cones or a vertex on a non-manifold edge. The winding
+    /// of the triangles does not matter.
+    pub fn non_manifold_vertices(&self) -> Vec<usize> {
+        (0..self.positions.len()).filter(|v| !self.is_manifold_vertex(*v)).collect()
+    }
+
+    /// Determine whether every edge and vertex of the mesh is manifold.
+    pub fn is_manifold(&self) -> bool {
+        self.non_manifold_edges().is_empty() && self.non_manifold_vertices().is_empty()
+    }
+}
+
+
+#[cfg(test)]
+mod halfedge_tests {
+    use super::HalfEdgeMesh;
+    use crate::ObjMesh;
+
+    fn from_triangles(triangles: &[[[f32; 3]; 3]]) -> ObjMesh {
+        let mut mesh = ObjMesh::default();
+        for (i, triangle) in triangles.iter().enumerate() {
+            let u = i as f32;
+            mesh.push_triangle(*triangle, [[u, 0.0], [u, 1.0], [u, 2.0]], [[0.0, 0.0, 1.0]; 3]);
+        }
+
+        mesh
+    }
+
+    /// A square split into four triangles around its center.
+    fn fan_mesh() -> ObjMesh {
+        let c = [0.0, 0.0, 0.0];
+        from_triangles(&[
+            [c, [1.0, -1.0, 0.0], [1.0, 1.0, 0.0]],
+            [c, [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0]],
+            [c, [-1.0, 1.0, 0.0], [-1.0, -1.0, 0.0]],
+            [c, [-1.0, -1.0, 0.0], [1.0, -1.0, 0.0]],
+        ])
+    }
+
+    #[test]
+    fn test_build_welds_vertices() {
+        let mesh = HalfEdgeMesh::from_obj_mesh(&fan_mesh(), 0.0);
+
+        assert_eq!(mesh.vertex_count(), 5);
+        assert_eq!(mesh.face_count(), 4);
+        assert_eq!(mesh.edge_count(), 8);
+        assert_eq!(mesh.boundary_half_edges().len(), 4);
+        assert!(mesh.is_manifold());
+    }
+
+    #[test]
+    fn test_round_trip_keeps_corner_attributes() {
+        let expected = fan_mesh();
+        let result = HalfEdgeMesh::from_obj_mesh(&expected, 0.0).to_obj_mesh();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_one_ring_and_edge_faces() {
+        let mesh = HalfEdgeMesh::from_obj_mesh(&fan_mesh(), 0.0);
+        let ring = mesh.one_ring(0);
+
+        assert_eq!(ring, vec![1, 2, 3, 4]);
+        assert_eq!(mesh.one_ring(1), vec![2, 0, 4]);
+        assert_eq!(mesh.edge_faces(0, 2), vec![0, 1]);
+        assert_eq!(mesh.edge_faces(1, 2), vec![0]);
+        assert!(mesh.edge_faces(1, 3).is_empty());
+        let h = mesh.outgoing(0)[0];
+        let twin = mesh.half_edge(h).twin.unwrap();
+        assert_eq!(mesh.target(twin), 0);
+    }
+
+    #[test]
+    fn test_non_manifold_edge() {
+        let a = [0.0, 0.0, 0.0];
+        let b = [1.0, 0.0, 0.0];
+        let mesh = from_triangles(&[[a, b, [0.0, 1.0, 0.0]], [b, a, [0.0, -1.0, 0.0]], [a, b, [0.0, 0.0, 1.0]]]);
+        let mesh = HalfEdgeMesh::from_obj_mesh(&mesh, 0.0);
+
+        assert_eq!(mesh.non_manifold_edges(), vec![(0, 1)]);
+        assert!(!mesh.is_manifold());
+    }
+
+    #[test]
+    fn test_non_manifold_vertex() {
+        let tip = [0.0, 0.0, 0.0];
+        let mesh = from_triangles(&[
+            [tip, [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]],
+            [tip, [-1.0, 0.0, 0.0], [-1.0, -1.0, 0.0]],
+        ]);
+        let mesh = HalfEdgeMesh::from_obj_mesh(&mesh, 0.0);
+
+        assert_eq!(mesh.non_manifold_vertices(), vec![0]);
+        assert!(mesh.non_manifold_edges().is_empty());
+        assert_eq!(mesh.one_ring(0).len(), 4);
+    }
+
+    #[test]
+    fn test_manifold_queries_ignore_winding() {
+        let c = [0.0, 0.0, 0.0];
+        let mesh = from_triangles(&[
+            [c, [1.0, -1.0, 0.0], [1.0, 1.0, 0.0]],
+            [c, [-1.0, 1.0, 0.0], [1.0, 1.0, 0.0]],
+            [c, [-1.0, 1.0, 0.0], [-1.0, -1.0, 0.0]],
+            [c, [-1.0, -1.0, 0.0], [1.0, -1.0, 0.0]],
+        ]);
+        let half_edge_mesh = HalfEdgeMesh::from_obj_mesh(&mesh, 0.0);
+
+        assert!(half_edge_mesh.non_manifold_vertices().is_empty());
+        assert!(half_edge_mesh.is_manifold());
+        assert_eq!(half_edge_mesh.is_manifold(), mesh.topology(0.0).is_manifold());
+        assert_eq!(half_edge_mesh.one_ring(0).len(), 4);
+    }
+}
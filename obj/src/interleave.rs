@@ -0,0 +1,281 @@
+//! Interleaved vertex buffers for object meshes.
+//!
+//! An interleaved buffer stores all the attributes of a vertex next to each
+//! other, in the order the caller asks for. The `VertexLayout` that comes
+//! with the buffer describes where each attribute lives, in the terms that
+//! `glVertexAttribPointer` and the Vulkan and wgpu vertex layouts expect.
+//!
+//! With the `bytemuck` feature enabled, the `Vertex` type is `Pod`, so a
+//! slice of vertices can be handed to a graphics API as bytes without any
+//! `unsafe` code.
+use std::mem;
+
+use crate::ObjMesh;
+
+
+/// A vertex attribute of an object mesh.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Attribute {
+    Position,
+    TexCoord,
+    Normal,
+    /// The vertex color, which is white for every vertex of a mesh without
+    /// vertex colors.
+    Color,
+}
+
+impl Attribute {
+    /// Get the number of components in the attribute.
+    #[inline]
+    pub fn components(self) -> usize {
+        match self {
+            Attribute::Position => 3,
+            Attribute::TexCoord => 2,
+            Attribute::Normal => 3,
+            Attribute::Color => 3,
+        }
+    }
+}
+
+/// The type of each component of a vertex attribute.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ComponentType {
+    Float32,
+}
+
+impl ComponentType {
+    /// Get the size of a single component in bytes.
+    #[inline]
+    pub fn size_bytes(self) -> usize {
+        match self {
+            ComponentType::Float32 => mem::size_of::<f32>(),
+        }
+    }
+}
+
+/// The placement of a single attribute inside an interleaved vertex.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AttributeLayout {
+    /// The attribute being described.
+    pub attribute: Attribute,
+    /// The offset of the attribute from the start of the vertex in bytes.
+    pub offset: usize,
+    /// The number of components in the attribute.
+    pub components: usize,
+    /// The type of each component.
+    pub component_type: ComponentType,
+}
+
+/// The layout of the vertices in an interleaved vertex buffer.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    /// The distance between the starts of consecutive vertices in bytes.
+    pub stride: usize,
+    /// The attributes of each vertex, in the order they are stored.
+    pub attributes: Vec<AttributeLayout>,
+}
+
+impl VertexLayout {
+    /// Construct the layout of a tightly packed vertex holding `order`'s
+    /// attributes in order.
+    pub fn new(order: &[Attribute]) -> VertexLayout {
+        let mut attributes = Vec::with_capacity(order.len());
+        let mut offset = 0;
+        for attribute in order.iter() {
+            let component_type = ComponentType::Float32;
+            attributes.push(AttributeLayout {
+                attribute: *attribute,
+                offset,
+                components: attribute.components(),
+                component_type,
+            });
+            offset += attribute.components() * component_type.size_bytes();
+        }
+
+        VertexLayout {
+            stride: offset,
+            attributes,
+        }
+    }
+
+    /// Find the placement of an attribute in the layout.
+    pub fn attribute(&self, attribute: Attribute) -> Option<&AttributeLayout> {
+        self.attributes.iter().find(|layout| layout.attribute == attribute)
+    }
+}
+
+/// A single vertex of an object mesh with all of its attributes, laid out as
+/// `[position, tex_coord, normal]` with no padding.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub tex_coord: [f32; 2],
+    pub normal: [f32; 3],
+}
+
+impl Vertex {
+    /// Get the layout of a `Vertex` in an interleaved vertex buffer.
+    pub fn layout() -> VertexLayout {
+        VertexLayout::new(&[Attribute::Position, Attribute::TexCoord, Attribute::Normal])
+    }
+}
+
+impl ObjMesh {
+    /// Collect the mesh buffers into a buffer of vertices, laid out as
+    /// described by `Vertex::layout`.
+    pub fn vertices(&self) -> Vec<Vertex> {
+        (0..self.len()).map(|i| Vertex {
+            position: self.points()[i],
+            tex_coord: self.tex_coords()[i],
+            normal: self.normals()[i],
+        }).collect()
+    }
+
+    /// Interleave the mesh buffers into one buffer of `f32` values, with the
+    /// attributes of each vertex stored in the order given by `order`.
+    pub fn interleaved(&self, order: &[Attribute]) -> (Vec<f32>, VertexLayout) {
+        let layout = VertexLayout::new(order);
+        let components = layout.stride / mem::size_of::<f32>();
+        let mut data = Vec::with_capacity(components * self.len());
+        for i in 0..self.len() {
+            for attribute in order.iter() {
+                match attribute {
+                    Attribute::Position => {
+                        data.extend_from_slice(&self.points()[i]);
+                    }
+                    Attribute::TexCoord => {
+                        data.extend_from_slice(&self.tex_coords()[i]);
+                    }
+                    Attribute::Normal => {
+                        data.extend_from_slice(&self.normals()[i]);
+                    }
+                    Attribute::Color => {
+                        data.extend_from_slice(&self.colors().map_or([1_f32; 3], |colors| colors[i]));
+                    }
+                }
+            }
+        }
+
+        (data, layout)
+    }
+
+    /// Interleave the mesh buffers into one buffer of bytes in native byte
+    /// order, ready to upload to a graphics API. See `interleaved`.
+    pub fn interleaved_bytes(&self, order: &[Attribute]) -> (Vec<u8>, VertexLayout) {
+        let (data, layout) = self.interleaved(order);
+        let mut bytes = Vec::with_capacity(data.len() * mem::size_of::<f32>());
+        for value in data.iter() {
+            bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+
+        (bytes, layout)
+    }
+}
+
+
+#[cfg(test)]
+mod interleave_tests {
+    use super::{Attribute, AttributeLayout, ComponentType, Vertex, VertexLayout};
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]];
+        let tex_coords = vec![[0.1, 0.2], [0.3, 0.4], [0.5, 0.6]];
+        let normals = vec![[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    #[test]
+    fn test_vertex_layout() {
+        let result = VertexLayout::new(&[Attribute::Position, Attribute::Normal, Attribute::TexCoord]);
+        let expected = VertexLayout {
+            stride: 32,
+            attributes: vec![
+                AttributeLayout {
+                    attribute: Attribute::Position, offset: 0, components: 3, component_type: ComponentType::Float32,
+                },
+                AttributeLayout {
+                    attribute: Attribute::Normal, offset: 12, components: 3, component_type: ComponentType::Float32,
+                },
+                AttributeLayout {
+                    attribute: Attribute::TexCoord, offset: 24, components: 2, component_type: ComponentType::Float32,
+                },
+            ],
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_interleaved_buffer() {
+        let mesh = test_mesh();
+        let (result, layout) = mesh.interleaved(&[Attribute::TexCoord, Attribute::Position]);
+        let expected = vec![
+            0.1, 0.2, 1.0, 2.0, 3.0,
+            0.3, 0.4, 4.0, 5.0, 6.0,
+            0.5, 0.6, 7.0, 8.0, 9.0,
+        ];
+
+        assert_eq!(result, expected);
+        assert_eq!(layout.stride, 20);
+        assert_eq!(layout.attribute(Attribute::Position).unwrap().offset, 8);
+        assert!(layout.attribute(Attribute::Normal).is_none());
+    }
+
+    #[test]
+    fn test_interleaved_colors() {
+        let mesh = test_mesh();
+        let colored = mesh.clone().with_colors(vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]).unwrap();
+        let (result, _) = colored.interleaved(&[Attribute::Color]);
+        let (white, _) = mesh.interleaved(&[Attribute::Color]);
+
+        assert_eq!(result, vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(white, vec![1.0; 9]);
+    }
+
+    #[test]
+    fn test_interleaved_bytes_match_interleaved_floats() {
+        let mesh = test_mesh();
+        let order = [Attribute::Position, Attribute::TexCoord, Attribute::Normal];
+        let (floats, _) = mesh.interleaved(&order);
+        let (bytes, layout) = mesh.interleaved_bytes(&order);
+
+        assert_eq!(bytes.len(), layout.stride * mesh.len());
+        for (i, value) in floats.iter().enumerate() {
+            let offset = 4 * i;
+            let result = f32::from_ne_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+            assert_eq!(result, *value);
+        }
+    }
+
+    #[test]
+    fn test_vertices_match_vertex_layout() {
+        let mesh = test_mesh();
+        let vertices = mesh.vertices();
+        let (expected, layout) = mesh.interleaved(&[Attribute::Position, Attribute::TexCoord, Attribute::Normal]);
+
+        assert_eq!(layout, Vertex::layout());
+        assert_eq!(std::mem::size_of::<Vertex>(), layout.stride);
+        for (i, vertex) in vertices.iter().enumerate() {
+            let values: Vec<f32> = [&vertex.position[..], &vertex.tex_coord[..], &vertex.normal[..]].concat();
+            assert_eq!(values.as_slice(), &expected[(8 * i)..(8 * i + 8)]);
+        }
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_vertices_as_bytes() {
+        let mesh = test_mesh();
+        let vertices = mesh.vertices();
+        let (expected, _) = mesh.interleaved_bytes(&[Attribute::Position, Attribute::TexCoord, Attribute::Normal]);
+        let result: &[u8] = bytemuck::cast_slice(&vertices);
+
+        assert_eq!(result, expected.as_slice());
+        assert_eq!(mesh.points.as_bytes().len(), mesh.points.len_bytes());
+        assert_eq!(mesh.tex_coords.as_bytes().len(), mesh.tex_coords.len_bytes());
+        assert_eq!(mesh.normals.as_bytes().len(), mesh.normals.len_bytes());
+    }
+}
@@ -1,4 +1,6 @@
 #![allow(dead_code)]
+use std::error;
+use std::fmt;
 use std::fs::File;
 use std::io;
 use std::io::BufReader;
@@ -11,8 +13,36 @@ use wavefront_obj::obj::{
     VTNTriple,
 };
 
-
-#[derive(Clone, Debug, PartialEq)]
+mod math;
+mod spatial;
+mod union_find;
+#[cfg(feature = "mint")]
+mod convert;
+pub mod binary;
+pub mod bounds;
+pub mod components;
+pub mod coordinates;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+pub mod halfedge;
+pub mod interleave;
+pub mod merge;
+pub mod normalize;
+pub mod optimize;
+pub mod orient;
+pub mod ply;
+pub mod quantize;
+pub mod simplify;
+pub mod stl;
+pub mod topology;
+pub mod transform;
+pub mod uv;
+pub mod validate;
+pub mod weld;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Points {
     inner: Vec<[f32; 3]>,
 }
@@ -34,9 +64,24 @@ impl Points {
     pub fn len(&self) -> usize {
         self.inner.len()
     }
+
+    /// Determine whether the points buffer is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Present the points buffer as raw bytes for uploading to the GPU.
+    #[cfg(feature = "bytemuck")]
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.inner)
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct TextureCoordinates {
     inner: Vec<[f32; 2]>,
 }
@@ -58,9 +103,24 @@ impl TextureCoordinates {
     pub fn len(&self) -> usize {
         self.inner.len()
     }
+
+    /// Determine whether the texture coordinates buffer is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Present the texture coordinates buffer as raw bytes for uploading to the GPU.
+    #[cfg(feature = "bytemuck")]
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.inner)
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Normals {
     inner: Vec<[f32; 3]>,
 }
@@ -82,28 +142,315 @@ impl Normals {
     pub fn len(&self) -> usize {
         self.inner.len()
     }
+
+    /// Determine whether the normal vector buffer is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Present the normal vector buffer as raw bytes for uploading to the GPU.
+    #[cfg(feature = "bytemuck")]
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.inner)
+    }
+}
+
+/// The vertex color buffer of a mesh, holding linear RGB values.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Colors {
+    inner: Vec<[f32; 3]>,
+}
+
+impl Colors {
+    #[inline]
+    pub fn as_ptr(&self) -> *const [f32; 3] {
+        self.inner.as_ptr()
+    }
+
+    /// Get the length of the color buffer in bytes.
+    #[inline]
+    pub fn len_bytes(&self) -> usize {
+        3 * mem::size_of::<f32>() * self.inner.len()
+    }
+
+    /// Get the number of elements in the color buffer.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Determine whether the color buffer is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Present the color buffer as raw bytes for uploading to the GPU.
+    #[cfg(feature = "bytemuck")]
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.inner)
+    }
 }
 
+/// The color of a vertex added to a colored mesh without one.
+const WHITE: [f32; 3] = [1_f32, 1_f32, 1_f32];
+
 /// An `ObjMesh` is a model space representation of a 3D geometric figure.
 /// You typically generate one from parsing a Wavefront *.obj file into
 /// an `ObjMesh`.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Every vertex of a mesh has a point, a texture coordinate, and a normal
+/// vector, so the three buffers always have the same length. A mesh may
+/// also carry a color for every vertex, for instance when it is loaded from
+/// a PLY file; the color buffer then has that same length too.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "ObjMeshBuffers"))]
 pub struct ObjMesh {
-    pub points: Points,
-    pub tex_coords: TextureCoordinates,
-    pub normals: Normals,
+    points: Points,
+    tex_coords: TextureCoordinates,
+    normals: Normals,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    colors: Option<Colors>,
+}
+
+/// An error from constructing an `ObjMesh` out of invalid buffers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ObjMeshError {
+    /// The points, texture coordinate, and normal buffers have different
+    /// lengths.
+    MismatchedBufferLengths {
+        points: usize,
+        tex_coords: usize,
+        normals: usize,
+    },
+    /// The color buffer has a different length than the points buffer.
+    MismatchedColorCount {
+        points: usize,
+        colors: usize,
+    },
+}
+
+impl fmt::Display for ObjMeshError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjMeshError::MismatchedBufferLengths { points, tex_coords, normals } => write!(
+                formatter,
+                "mesh buffers have mismatched lengths: {} points, {} texture coordinates, {} normals",
+                points, tex_coords, normals
+            ),
+            ObjMeshError::MismatchedColorCount { points, colors } => write!(
+                formatter,
+                "mesh has {} points but {} vertex colors",
+                points, colors
+            ),
+        }
+    }
+}
+
+impl error::Error for ObjMeshError {}
+
+/// The unchecked buffers of a serialized mesh.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ObjMeshBuffers {
+    points: Vec<[f32; 3]>,
+    tex_coords: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+    #[serde(default)]
+    colors: Option<Vec<[f32; 3]>>,
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<ObjMeshBuffers> for ObjMesh {
+    type Error = ObjMeshError;
+
+    fn try_from(buffers: ObjMeshBuffers) -> Result<ObjMesh, ObjMeshError> {
+        let mesh = ObjMesh::try_new(buffers.points, buffers.tex_coords, buffers.normals)?;
+        match buffers.colors {
+            Some(colors) => mesh.with_colors(colors),
+            None => Ok(mesh),
+        }
+    }
 }
 
 impl ObjMesh {
     /// Generate a new mesh object.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the buffers have different lengths. Use `try_new` to
+    /// handle this case instead.
     pub fn new(points: Vec<[f32; 3]>, tex_coords: Vec<[f32; 2]>, normals: Vec<[f32; 3]>) -> ObjMesh {
-        ObjMesh {
+        match ObjMesh::try_new(points, tex_coords, normals) {
+            Ok(mesh) => mesh,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Generate a new mesh object, or return an error when the buffers have
+    /// different lengths.
+    pub fn try_new(
+        points: Vec<[f32; 3]>,
+        tex_coords: Vec<[f32; 2]>,
+        normals: Vec<[f32; 3]>,
+    ) -> Result<ObjMesh, ObjMeshError> {
+        if tex_coords.len() != points.len() || normals.len() != points.len() {
+            return Err(ObjMeshError::MismatchedBufferLengths {
+                points: points.len(),
+                tex_coords: tex_coords.len(),
+                normals: normals.len(),
+            });
+        }
+
+        Ok(ObjMesh {
             points: Points { inner: points },
             tex_coords: TextureCoordinates { inner: tex_coords },
             normals: Normals { inner: normals },
+            colors: None,
+        })
+    }
+
+    /// Give every vertex of the mesh a color, or return an error when the
+    /// number of colors differs from the number of vertices.
+    pub fn with_colors(mut self, colors: Vec<[f32; 3]>) -> Result<ObjMesh, ObjMeshError> {
+        if colors.len() != self.len() {
+            return Err(ObjMeshError::MismatchedColorCount { points: self.len(), colors: colors.len() });
+        }
+        self.colors = Some(Colors { inner: colors });
+
+        Ok(self)
+    }
+
+    /// Remove the vertex colors from the mesh.
+    pub fn clear_colors(&mut self) {
+        self.colors = None;
+    }
+
+    /// Generate an empty mesh with room for `capacity` vertices.
+    pub fn with_capacity(capacity: usize) -> ObjMesh {
+        ObjMesh {
+            points: Points { inner: Vec::with_capacity(capacity) },
+            tex_coords: TextureCoordinates { inner: Vec::with_capacity(capacity) },
+            normals: Normals { inner: Vec::with_capacity(capacity) },
+            colors: None,
+        }
+    }
+
+    /// Append a vertex to the mesh. When the mesh has vertex colors, the new
+    /// vertex is white.
+    #[inline]
+    pub fn push_vertex(&mut self, point: [f32; 3], tex_coord: [f32; 2], normal: [f32; 3]) {
+        self.points.inner.push(point);
+        self.tex_coords.inner.push(tex_coord);
+        self.normals.inner.push(normal);
+        if let Some(colors) = &mut self.colors {
+            colors.inner.push(WHITE);
         }
     }
 
+    /// Append a vertex with a color to the mesh. When the mesh has no vertex
+    /// colors yet, every vertex already in it becomes white.
+    pub fn push_colored_vertex(&mut self, point: [f32; 3], tex_coord: [f32; 2], normal: [f32; 3], color: [f32; 3]) {
+        let len = self.len();
+        let colors = self.colors.get_or_insert_with(|| Colors { inner: vec![WHITE; len] });
+        colors.inner.push(color);
+        self.points.inner.push(point);
+        self.tex_coords.inner.push(tex_coord);
+        self.normals.inner.push(normal);
+    }
+
+    /// Append vertex `index` of another mesh to the mesh, along with its
+    /// color when the other mesh has one.
+    pub(crate) fn push_vertex_from(&mut self, other: &ObjMesh, index: usize) {
+        let point = other.points()[index];
+        let tex_coord = other.tex_coords()[index];
+        let normal = other.normals()[index];
+        match other.colors() {
+            Some(colors) => self.push_colored_vertex(point, tex_coord, normal, colors[index]),
+            None => self.push_vertex(point, tex_coord, normal),
+        }
+    }
+
+    /// Swap two vertices of the mesh, with every one of their attributes.
+    pub(crate) fn swap_vertices(&mut self, a: usize, b: usize) {
+        self.points.inner.swap(a, b);
+        self.tex_coords.inner.swap(a, b);
+        self.normals.inner.swap(a, b);
+        if let Some(colors) = &mut self.colors {
+            colors.inner.swap(a, b);
+        }
+    }
+
+    /// Append a triangle to the mesh.
+    pub fn push_triangle(&mut self, points: [[f32; 3]; 3], tex_coords: [[f32; 2]; 3], normals: [[f32; 3]; 3]) {
+        for i in 0..3 {
+            self.push_vertex(points[i], tex_coords[i], normals[i]);
+        }
+    }
+
+    /// Append the vertices of another mesh to the mesh. When only one of the
+    /// two meshes has vertex colors, the vertices of the other one are white.
+    pub fn extend_from_mesh(&mut self, other: &ObjMesh) {
+        if other.colors.is_some() && self.colors.is_none() {
+            self.colors = Some(Colors { inner: vec![WHITE; self.len()] });
+        }
+        if let Some(colors) = &mut self.colors {
+            match other.colors() {
+                Some(other_colors) => colors.inner.extend_from_slice(other_colors),
+                None => colors.inner.resize(colors.inner.len() + other.len(), WHITE),
+            }
+        }
+        self.points.inner.extend_from_slice(other.points());
+        self.tex_coords.inner.extend_from_slice(other.tex_coords());
+        self.normals.inner.extend_from_slice(other.normals());
+    }
+
+    /// Shorten the mesh to its first `len` vertices. This has no effect when
+    /// the mesh is already shorter.
+    pub fn truncate(&mut self, len: usize) {
+        self.points.inner.truncate(len);
+        self.tex_coords.inner.truncate(len);
+        self.normals.inner.truncate(len);
+        if let Some(colors) = &mut self.colors {
+            colors.inner.truncate(len);
+        }
+    }
+
+    /// Remove every vertex from the mesh.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Get the points buffer.
+    #[inline]
+    pub fn points_buffer(&self) -> &Points {
+        &self.points
+    }
+
+    /// Get the texture coordinates buffer.
+    #[inline]
+    pub fn tex_coords_buffer(&self) -> &TextureCoordinates {
+        &self.tex_coords
+    }
+
+    /// Get the normal vector buffer.
+    #[inline]
+    pub fn normals_buffer(&self) -> &Normals {
+        &self.normals
+    }
+
+    /// Get the vertex color buffer, if the mesh has vertex colors.
+    #[inline]
+    pub fn colors_buffer(&self) -> Option<&Colors> {
+        self.colors.as_ref()
+    }
+
     /// Present the points map as an array slice. This function can be used
     /// to present the internal array buffer to OpenGL or another Graphics
     /// system for rendering.
@@ -128,11 +475,59 @@ impl ObjMesh {
         &self.normals.inner
     }
 
+    /// Present the vertex colors as an array slice, if the mesh has vertex
+    /// colors.
+    #[inline]
+    pub fn colors(&self) -> Option<&[[f32; 3]]> {
+        self.colors.as_ref().map(|colors| colors.inner.as_slice())
+    }
+
     /// Get the number of vertices in the mesh.
     #[inline]
     pub fn len(&self) -> usize {
         self.points.len()
     }
+
+    /// Determine whether the mesh has no vertices.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+}
+
+/// Post-processing steps to apply to a mesh as it is loaded.
+///
+/// The default options leave the mesh exactly as it was stored.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LoadOptions {
+    /// Convert the mesh from the first coordinate system into the second.
+    /// This is applied before any of the other steps.
+    pub convert_coordinates: Option<(coordinates::CoordinateSystem, coordinates::CoordinateSystem)>,
+    /// Translate the mesh so that this center point lies at the origin. This
+    /// is ignored when `fit` is set, since fitting centers the mesh itself.
+    pub recenter: Option<normalize::Center>,
+    /// Uniformly scale and center the mesh to fit inside a unit volume. The
+    /// center is the one the volume calls for, so `recenter` is ignored.
+    pub fit: Option<normalize::Fit>,
+}
+
+impl LoadOptions {
+    /// Apply the options to a mesh, returning the combined transform so that
+    /// it can be undone with `transform::inverse`. This works on meshes from
+    /// any of the loaders.
+    pub fn apply(&self, mesh: &mut ObjMesh) -> [[f32; 4]; 4] {
+        let mut matrix = transform::IDENTITY;
+        if let Some((from, to)) = self.convert_coordinates {
+            matrix = mesh.convert_coordinates(&from, &to);
+        }
+        if let Some(fit) = self.fit {
+            matrix = transform::multiply(&mesh.fit(fit), &matrix);
+        } else if let Some(center) = self.recenter {
+            matrix = transform::multiply(&mesh.recenter(center), &matrix);
+        }
+
+        matrix
+    }
 }
 
 pub fn load<R: io::Read>(reader: &mut R) -> Result<ObjMesh, String> {
@@ -182,7 +577,7 @@ pub fn load<R: io::Read>(reader: &mut R) -> Result<ObjMesh, String> {
         }
     }
 
-    Ok(ObjMesh::new(vertices, tex_coords, normals))
+    ObjMesh::try_new(vertices, tex_coords, normals).map_err(|e| format!("ERROR: {}", e))
 }
 
 pub fn load_from_memory(buffer: &[u8]) -> Result<ObjMesh, String> {
@@ -202,6 +597,20 @@ pub fn load_file<P: AsRef<Path>>(path: P) -> Result<ObjMesh, String> {
     load(&mut reader)
 }
 
+pub fn load_with_options<R: io::Read>(reader: &mut R, options: &LoadOptions) -> Result<ObjMesh, String> {
+    let mut mesh = load(reader)?;
+    options.apply(&mut mesh);
+
+    Ok(mesh)
+}
+
+pub fn load_file_with_options<P: AsRef<Path>>(path: P, options: &LoadOptions) -> Result<ObjMesh, String> {
+    let mut mesh = load_file(path)?;
+    options.apply(&mut mesh);
+
+    Ok(mesh)
+}
+
 
 #[cfg(test)]
 mod loader_tests {
@@ -319,3 +728,121 @@ mod loader_tests {
         assert_eq!(result, expected);
     }
 }
+
+
+#[cfg(test)]
+mod mesh_tests {
+    use super::{ObjMesh, ObjMeshError};
+
+    #[test]
+    fn test_try_new_rejects_mismatched_buffers() {
+        let result = ObjMesh::try_new(vec![[0.0, 0.0, 0.0]; 3], vec![[0.0, 0.0]; 2], vec![[0.0, 0.0, 1.0]; 3]);
+        let expected = ObjMeshError::MismatchedBufferLengths { points: 3, tex_coords: 2, normals: 3 };
+
+        assert_eq!(result, Err(expected));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_panics_on_mismatched_buffers() {
+        ObjMesh::new(vec![[0.0, 0.0, 0.0]; 3], vec![], vec![]);
+    }
+
+    #[test]
+    fn test_builder_keeps_buffers_in_sync() {
+        let mut mesh = ObjMesh::with_capacity(6);
+        mesh.push_triangle(
+            [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]],
+            [[0.0, 0.0, 1.0]; 3],
+        );
+        let copy = mesh.clone();
+        mesh.extend_from_mesh(&copy);
+        mesh.push_vertex([2.0, 2.0, 2.0], [0.5, 0.5], [1.0, 0.0, 0.0]);
+
+        assert_eq!(mesh.len(), 7);
+        assert_eq!(mesh.tex_coords().len(), 7);
+        assert_eq!(mesh.normals().len(), 7);
+        assert_eq!(mesh.points_buffer().len_bytes(), 7 * 12);
+
+        mesh.truncate(3);
+        assert_eq!(mesh, copy);
+        mesh.clear();
+        assert!(mesh.is_empty());
+        assert!(mesh.tex_coords_buffer().is_empty() && mesh.normals_buffer().is_empty());
+    }
+
+    #[test]
+    fn test_colors_stay_in_sync_with_vertices() {
+        let triangle = ObjMesh::new(vec![[0.0, 0.0, 0.0]; 3], vec![[0.0, 0.0]; 3], vec![[0.0, 0.0, 1.0]; 3]);
+        let red = triangle.clone().with_colors(vec![[1.0, 0.0, 0.0]; 3]).unwrap();
+        let mut mesh = triangle.clone();
+        mesh.extend_from_mesh(&red);
+        mesh.push_vertex([1.0, 1.0, 1.0], [0.0, 0.0], [0.0, 0.0, 1.0]);
+        mesh.push_colored_vertex([1.0, 1.0, 1.0], [0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0]);
+
+        let expected = [vec![[1.0, 1.0, 1.0]; 3], vec![[1.0, 0.0, 0.0]; 3], vec![[1.0, 1.0, 1.0], [0.0, 0.0, 1.0]]].concat();
+        assert_eq!(mesh.colors(), Some(expected.as_slice()));
+
+        mesh.truncate(3);
+        assert_eq!(mesh.colors_buffer().map(|colors| colors.len()), Some(3));
+        mesh.clear_colors();
+        assert_eq!(mesh, triangle);
+
+        let expected = ObjMeshError::MismatchedColorCount { points: 3, colors: 2 };
+        assert_eq!(triangle.with_colors(vec![[0.0; 3]; 2]), Err(expected));
+    }
+}
+
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let tex_coords = vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+        let normals = vec![[0.0, 0.0, 1.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0]];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    #[test]
+    fn test_obj_mesh_serializes_buffers_as_arrays() {
+        let mesh = test_mesh();
+        let result = serde_json::to_string(&mesh).unwrap();
+        let expected = concat!(
+            "{\"points\":[[0.0,0.0,0.0],[1.0,0.0,0.0],[0.0,1.0,0.0]],",
+            "\"tex_coords\":[[0.0,0.0],[1.0,0.0],[0.0,1.0]],",
+            "\"normals\":[[0.0,0.0,1.0],[0.0,0.0,1.0],[0.0,0.0,1.0]]}"
+        );
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_obj_mesh_serde_round_trip() {
+        let expected = test_mesh();
+        let json = serde_json::to_string(&expected).unwrap();
+        let result: ObjMesh = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_obj_mesh_serde_round_trip_with_colors() {
+        let expected = test_mesh().with_colors(vec![[1.0, 0.0, 0.0]; 3]).unwrap();
+        let json = serde_json::to_string(&expected).unwrap();
+        let result: ObjMesh = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_obj_mesh_deserialization_checks_buffer_lengths() {
+        let json = "{\"points\":[[0.0,0.0,0.0]],\"tex_coords\":[],\"normals\":[[0.0,0.0,1.0]]}";
+        let result = serde_json::from_str::<ObjMesh>(json);
+
+        assert!(result.is_err());
+    }
+}
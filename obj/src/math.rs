@@ -0,0 +1,53 @@
+//! Small vector helpers shared by the mesh formats and mesh operations.
+
+
+#[inline]
+pub(crate) fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+#[inline]
+pub(crate) fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+#[inline]
+pub(crate) fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+#[inline]
+pub(crate) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline]
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[inline]
+pub(crate) fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// Normalize a vector, returning the zero vector when its length is zero.
+#[inline]
+pub(crate) fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = length(a);
+    if len > 0_f32 {
+        scale(a, 1_f32 / len)
+    } else {
+        [0_f32, 0_f32, 0_f32]
+    }
+}
+
+/// Compute the unit normal of the triangle `(a, b, c)` with counterclockwise winding.
+#[inline]
+pub(crate) fn triangle_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    normalize(cross(sub(b, a), sub(c, a)))
+}
@@ -0,0 +1,126 @@
+//! Merging several object meshes into one.
+//!
+//! A merged mesh remembers which vertices came from which source mesh as a
+//! list of submesh ranges, so it can be drawn in one call and still be taken
+//! apart again.
+use std::ops::Range;
+
+use crate::ObjMesh;
+
+
+impl ObjMesh {
+    /// Concatenate meshes into one mesh, returning it with the range of
+    /// vertices that came from each source mesh.
+    ///
+    /// The sources may have different attribute sets: a source loaded without
+    /// texture coordinates or normals holds zeros for them, and keeps those
+    /// zeros in the merged mesh. When only some sources have vertex colors,
+    /// the vertices of the others are white. Each source should hold whole
+    /// triangles, so that every range starts on a triangle.
+    pub fn merge<'a, I>(meshes: I) -> (ObjMesh, Vec<Range<usize>>)
+    where
+        I: IntoIterator<Item = &'a ObjMesh>,
+    {
+        let mut merged = ObjMesh::default();
+        let mut submeshes = vec![];
+        for mesh in meshes {
+            let start = merged.len();
+            merged.extend_from_mesh(mesh);
+            submeshes.push(start..merged.len());
+        }
+
+        (merged, submeshes)
+    }
+
+    /// Concatenate meshes into one mesh after transforming each one by its
+    /// own affine matrix. See `merge` and `transform`.
+    pub fn merge_transformed<'a, I>(meshes: I) -> (ObjMesh, Vec<Range<usize>>)
+    where
+        I: IntoIterator<Item = (&'a ObjMesh, &'a [[f32; 4]; 4])>,
+    {
+        let transformed: Vec<ObjMesh> = meshes.into_iter().map(|(mesh, matrix)| {
+            let mut mesh = mesh.clone();
+            mesh.transform(matrix);
+            mesh
+        }).collect();
+
+        ObjMesh::merge(transformed.iter())
+    }
+
+    /// Copy a range of vertices out of the mesh, such as one of the submesh
+    /// ranges returned by `merge`.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the range extends past the end of the mesh.
+    pub fn submesh(&self, range: Range<usize>) -> ObjMesh {
+        let mesh = ObjMesh::new(
+            self.points()[range.clone()].to_vec(),
+            self.tex_coords()[range.clone()].to_vec(),
+            self.normals()[range.clone()].to_vec(),
+        );
+
+        match self.colors() {
+            Some(colors) => mesh.with_colors(colors[range].to_vec()).unwrap(),
+            None => mesh,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod merge_tests {
+    use crate::transform;
+    use crate::ObjMesh;
+
+    fn triangle(offset: f32) -> ObjMesh {
+        let points = vec![[offset, 0.0, 0.0], [offset + 1.0, 0.0, 0.0], [offset, 1.0, 0.0]];
+        let tex_coords = vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+        let normals = vec![[0.0, 0.0, 1.0]; 3];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    #[test]
+    fn test_merge_keeps_submesh_ranges() {
+        let meshes = [triangle(0.0), triangle(5.0), triangle(10.0)];
+        let (merged, submeshes) = ObjMesh::merge(meshes.iter());
+
+        assert_eq!(merged.len(), 9);
+        assert_eq!(submeshes, vec![0..3, 3..6, 6..9]);
+        for (mesh, range) in meshes.iter().zip(submeshes.iter()) {
+            assert_eq!(&merged.submesh(range.clone()), mesh);
+        }
+    }
+
+    #[test]
+    fn test_merge_fills_missing_colors_and_skips_empty_meshes() {
+        let red = triangle(0.0).with_colors(vec![[1.0, 0.0, 0.0]; 3]).unwrap();
+        let plain = triangle(1.0);
+        let empty = ObjMesh::default();
+        let (merged, submeshes) = ObjMesh::merge(vec![&empty, &red, &plain, &empty]);
+        let expected_colors = [[[1.0, 0.0, 0.0]; 3], [[1.0, 1.0, 1.0]; 3]].concat();
+
+        assert_eq!(submeshes, vec![0..0, 0..3, 3..6, 6..6]);
+        assert_eq!(merged.colors(), Some(expected_colors.as_slice()));
+        assert_eq!(merged.submesh(submeshes[1].clone()), red);
+        assert_eq!(merged.submesh(submeshes[2].clone()).points(), plain.points());
+        assert!(merged.submesh(submeshes[3].clone()).is_empty());
+
+        let (merged, _) = ObjMesh::merge(vec![&plain, &empty, &plain]);
+        assert_eq!(merged.colors(), None);
+    }
+
+    #[test]
+    fn test_merge_transformed() {
+        let mesh = triangle(0.0);
+        let moved = transform::translation([0.0, 0.0, 2.0]);
+        let mirrored = transform::scaling([1.0, 1.0, -1.0]);
+        let (merged, submeshes) = ObjMesh::merge_transformed(vec![(&mesh, &moved), (&mesh, &mirrored)]);
+        let mut expected = mesh.clone();
+        expected.transform(&mirrored);
+
+        assert_eq!(merged.points()[0], [0.0, 0.0, 2.0]);
+        assert_eq!(merged.submesh(submeshes[1].clone()), expected);
+    }
+}
@@ -0,0 +1,220 @@
+//! Recentering and rescaling object meshes into a standard frame.
+//!
+//! Each operation returns the matrix it applied, so the original placement of
+//! a mesh can be restored with `transform::inverse`.
+use crate::math;
+use crate::transform;
+use crate::ObjMesh;
+
+
+/// A choice of center point for a mesh.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Center {
+    /// The area-weighted centroid of the triangles, or the mean of the points
+    /// when the triangles have no area.
+    Centroid,
+    /// The center of the axis-aligned bounding box.
+    BoundsCenter,
+}
+
+/// A volume to fit a mesh into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Fit {
+    /// The cube with sides of length one centered at the origin. The mesh is
+    /// centered on its bounding box center and its longest side is scaled to
+    /// length one.
+    UnitCube,
+    /// The sphere of radius one centered at the origin. The mesh is centered
+    /// on its bounding sphere and scaled so the sphere has radius one.
+    UnitSphere,
+}
+
+impl ObjMesh {
+    /// Compute a center point of the mesh, or `None` when the mesh has no
+    /// points.
+    pub fn center(&self, center: Center) -> Option<[f32; 3]> {
+        match center {
+            Center::BoundsCenter => self.aabb().map(|aabb| aabb.center()),
+            Center::Centroid => {
+                let points = self.points();
+                if points.is_empty() {
+                    return None;
+                }
+
+                let mut weighted = [0_f64; 3];
+                let mut total_area = 0_f64;
+                for triangle in points.chunks_exact(3) {
+                    let area = 0.5 * math::length(math::cross(
+                        math::sub(triangle[1], triangle[0]),
+                        math::sub(triangle[2], triangle[0]),
+                    )) as f64;
+                    for i in 0..3 {
+                        let centroid = (triangle[0][i] + triangle[1][i] + triangle[2][i]) as f64 / 3.0;
+                        weighted[i] += area * centroid;
+                    }
+                    total_area += area;
+                }
+
+                if total_area > 0.0 {
+                    return Some([
+                        (weighted[0] / total_area) as f32,
+                        (weighted[1] / total_area) as f32,
+                        (weighted[2] / total_area) as f32,
+                    ]);
+                }
+
+                let mut mean = [0_f64; 3];
+                for point in points.iter() {
+                    for i in 0..3 {
+                        mean[i] += point[i] as f64 / points.len() as f64;
+                    }
+                }
+
+                Some([mean[0] as f32, mean[1] as f32, mean[2] as f32])
+            }
+        }
+    }
+
+    /// Translate the mesh so that the chosen center point lies at the origin,
+    /// returning the applied transform.
+    pub fn recenter(&mut self, center: Center) -> [[f32; 4]; 4] {
+        let matrix = match self.center(center) {
+            Some(point) => transform::translation(math::scale(point, -1_f32)),
+            None => transform::IDENTITY,
+        };
+        self.transform(&matrix);
+
+        matrix
+    }
+
+    /// Uniformly scale and translate the mesh to fit inside a unit volume,
+    /// returning the applied transform. A mesh with no extent is centered but
+    /// not scaled.
+    pub fn fit(&mut self, fit: Fit) -> [[f32; 4]; 4] {
+        let (center, size) = match fit {
+            Fit::UnitCube => match self.aabb() {
+                Some(aabb) => {
+                    let size = aabb.size();
+                    (aabb.center(), f32::max(size[0], f32::max(size[1], size[2])))
+                }
+                None => return transform::IDENTITY,
+            },
+            Fit::UnitSphere => match self.bounding_sphere() {
+                Some(sphere) => (sphere.center, sphere.radius),
+                None => return transform::IDENTITY,
+            },
+        };
+
+        let factor = if size > 0_f32 && size.is_finite() { 1_f32 / size } else { 1_f32 };
+        let matrix = transform::multiply(
+            &transform::scaling([factor, factor, factor]),
+            &transform::translation(math::scale(center, -1_f32)),
+        );
+        self.transform(&matrix);
+
+        matrix
+    }
+}
+
+
+#[cfg(test)]
+mod normalize_tests {
+    use super::{Center, Fit};
+    use crate::math;
+    use crate::transform;
+    use crate::{LoadOptions, ObjMesh};
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![
+            [2.0, 1.0, 1.0], [6.0, 1.0, 1.0], [6.0, 3.0, 1.0],
+            [2.0, 1.0, 1.0], [6.0, 3.0, 1.0], [2.0, 3.0, 2.0],
+        ];
+        let normals = vec![[0.0, 0.0, 1.0]; 6];
+
+        ObjMesh::new(points, vec![[0.0, 0.0]; 6], normals)
+    }
+
+    #[test]
+    fn test_recenter_on_bounds_center() {
+        let mut mesh = test_mesh();
+        let matrix = mesh.recenter(Center::BoundsCenter);
+        let aabb = mesh.aabb().unwrap();
+
+        assert_eq!(aabb.center(), [0.0, 0.0, 0.0]);
+        assert_eq!(matrix, transform::translation([-4.0, -2.0, -1.5]));
+    }
+
+    #[test]
+    fn test_recenter_on_centroid() {
+        let points = vec![[0.0, 0.0, 0.0], [3.0, 0.0, 0.0], [0.0, 3.0, 0.0]];
+        let mut mesh = ObjMesh::new(points, vec![[0.0, 0.0]; 3], vec![[0.0, 0.0, 1.0]; 3]);
+
+        assert_eq!(mesh.center(Center::Centroid), Some([1.0, 1.0, 0.0]));
+        mesh.recenter(Center::Centroid);
+        assert_eq!(mesh.center(Center::Centroid), Some([0.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn test_fit_to_unit_cube() {
+        let mut mesh = test_mesh();
+        mesh.fit(Fit::UnitCube);
+        let aabb = mesh.aabb().unwrap();
+
+        assert_eq!(aabb.min, [-0.5, -0.25, -0.125]);
+        assert_eq!(aabb.max, [0.5, 0.25, 0.125]);
+    }
+
+    #[test]
+    fn test_fit_to_unit_sphere() {
+        let mut mesh = test_mesh();
+        mesh.fit(Fit::UnitSphere);
+        let sphere = mesh.bounding_sphere().unwrap();
+
+        assert!(math::length(sphere.center) < 1e-5);
+        assert!((sphere.radius - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_fit_can_be_undone() {
+        let expected = test_mesh();
+        let mut mesh = test_mesh();
+        let matrix = mesh.fit(Fit::UnitCube);
+        mesh.transform(&transform::inverse(&matrix).unwrap());
+
+        for (result, expected) in mesh.points().iter().zip(expected.points().iter()) {
+            assert!(math::length(math::sub(*result, *expected)) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_load_options_report_combined_transform() {
+        let options = LoadOptions {
+            recenter: Some(Center::Centroid),
+            fit: Some(Fit::UnitCube),
+            ..LoadOptions::default()
+        };
+        let mut result = test_mesh();
+        let matrix = options.apply(&mut result);
+        let mut expected = test_mesh();
+        expected.transform(&matrix);
+
+        assert_eq!(result.aabb().unwrap().center(), [0.0, 0.0, 0.0]);
+        for (result, expected) in result.points().iter().zip(expected.points().iter()) {
+            assert!(math::length(math::sub(*result, *expected)) < 1e-5);
+        }
+        assert_eq!(LoadOptions::default().apply(&mut test_mesh()), transform::IDENTITY);
+    }
+
+    #[test]
+    fn test_load_options_fit_ignores_recenter() {
+        let fit_only = LoadOptions { fit: Some(Fit::UnitSphere), ..LoadOptions::default() };
+        let both = LoadOptions { recenter: Some(Center::Centroid), ..fit_only };
+        let mut expected = test_mesh();
+        let expected_matrix = fit_only.apply(&mut expected);
+        let mut result = test_mesh();
+        let matrix = both.apply(&mut result);
+
+        assert_eq!(matrix, expected_matrix);
+        assert_eq!(result, expected);
+    }
+}
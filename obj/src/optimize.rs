@@ -0,0 +1,395 @@
+//! Optimization of index buffers for rendering.
+//!
+//! After welding, an `ObjMesh` can be drawn as an index buffer into a vertex
+//! buffer of distinct vertices. How fast the GPU draws it depends on the order
+//! of the triangles: the post-transform vertex cache reuses recently shaded
+//! vertices, and triangles drawn front to back let early depth testing skip
+//! hidden pixels. The order of the vertices matters too, since vertices
+//! fetched in the order they are used make better use of memory caches.
+//!
+//! The passes here work on index buffers, so their results carry over to any
+//! vertex format. The triangle order is optimized with the Tipsify algorithm
+//! of Sander, Nehab, and Barczak, "Fast Triangle Reordering for Vertex
+//! Locality and Reduced Overdraw", which also supplies the clustering used to
+//! reduce overdraw. `CacheStats` measures the effect by simulating a FIFO
+//! vertex cache.
+use std::collections::VecDeque;
+
+use crate::math;
+use crate::weld::WeldOptions;
+use crate::ObjMesh;
+
+
+/// The efficiency of an index buffer on a simulated FIFO vertex cache.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CacheStats {
+    /// The number of vertices shaded, counting every cache miss.
+    pub misses: usize,
+    /// The average cache miss ratio: vertices shaded per triangle. It ranges
+    /// from about 0.5 for a perfect order on a large grid to 3.
+    pub acmr: f32,
+    /// The average transformed vertex ratio: vertices shaded per distinct
+    /// vertex. It is 1 for a perfect order.
+    pub atvr: f32,
+}
+
+/// Simulate drawing the triangles of an index buffer through a FIFO vertex
+/// cache holding `cache_size` vertices. Indices after the last whole triangle
+/// are ignored.
+pub fn cache_stats(indices: &[usize], cache_size: usize) -> CacheStats {
+    let indices = &indices[..(indices.len() - indices.len() % 3)];
+    let mut cache = VecDeque::with_capacity(cache_size + 1);
+    let mut misses = 0;
+    for index in indices.iter() {
+        if !cache.contains(index) {
+            misses += 1;
+            cache.push_back(*index);
+            if cache.len() > cache_size {
+                cache.pop_front();
+            }
+        }
+    }
+
+    let mut unique = indices.to_vec();
+    unique.sort_unstable();
+    unique.dedup();
+    let ratio = |count: usize| if count > 0 { misses as f32 / count as f32 } else { 0_f32 };
+
+    CacheStats { misses, acmr: ratio(indices.len() / 3), atvr: ratio(unique.len()) }
+}
+
+/// Order the triangles of an index buffer with the Tipsify algorithm. Returns
+/// the triangles in drawing order.
+fn tipsify(indices: &[usize], vertex_count: usize, cache_size: usize) -> Vec<usize> {
+    let triangle_count = indices.len() / 3;
+    let mut adjacency = vec![vec![]; vertex_count];
+    for triangle in 0..triangle_count {
+        for index in indices[(3 * triangle)..(3 * triangle + 3)].iter() {
+            adjacency[*index].push(triangle);
+        }
+    }
+
+    let mut live: Vec<usize> = adjacency.iter().map(|triangles| triangles.len()).collect();
+    let mut emitted = vec![false; triangle_count];
+    let mut cache_times = vec![0; vertex_count];
+    let mut time = cache_size + 1;
+    let mut dead_ends = vec![];
+    let mut cursor = 0;
+    let mut order = Vec::with_capacity(triangle_count);
+    let mut fanning = if triangle_count > 0 { Some(indices[0]) } else { None };
+    while let Some(vertex) = fanning {
+        let mut candidates = vec![];
+        for triangle in adjacency[vertex].iter() {
+            if emitted[*triangle] {
+                continue;
+            }
+
+            for index in indices[(3 * triangle)..(3 * triangle + 3)].iter() {
+                dead_ends.push(*index);
+                candidates.push(*index);
+                live[*index] -= 1;
+                if time - cache_times[*index] > cache_size {
+                    cache_times[*index] = time;
+                    time += 1;
+                }
+            }
+            emitted[*triangle] = true;
+            order.push(*triangle);
+        }
+
+        // Fan around the candidate that will stay in the cache the longest
+        // while its remaining triangles are drawn.
+        let mut best = None;
+        let mut best_priority = 0;
+        for candidate in candidates.iter().filter(|candidate| live[**candidate] > 0) {
+            let age = time - cache_times[*candidate];
+            let priority = if age + 2 * live[*candidate] <= cache_size { age } else { 0 };
+            if best.is_none() || priority > best_priority {
+                best = Some(*candidate);
+                best_priority = priority;
+            }
+        }
+
+        fanning = best.or_else(|| {
+            while let Some(vertex) = dead_ends.pop() {
+                if live[vertex] > 0 {
+                    return Some(vertex);
+                }
+            }
+            while cursor < vertex_count {
+                cursor += 1;
+                if live[cursor - 1] > 0 {
+                    return Some(cursor - 1);
+                }
+            }
+
+            None
+        });
+    }
+
+    order
+}
+
+/// Split triangles in drawing order into clusters that start where the
+/// vertex cache has nothing to offer, so that reordering the clusters barely
+/// affects the cache, and sort the clusters so that those facing away from
+/// the center of the mesh come first.
+fn sort_clusters(indices: &[usize], positions: &[[f32; 3]], cache_size: usize) -> Vec<usize> {
+    let triangle_count = indices.len() / 3;
+    let mut cache = VecDeque::with_capacity(cache_size + 1);
+    let mut clusters: Vec<Vec<usize>> = vec![];
+    for triangle in 0..triangle_count {
+        let mut misses = 0;
+        for index in indices[(3 * triangle)..(3 * triangle + 3)].iter() {
+            if !cache.contains(index) {
+                misses += 1;
+                cache.push_back(*index);
+                if cache.len() > cache_size {
+                    cache.pop_front();
+                }
+            }
+        }
+        match clusters.last_mut() {
+            Some(cluster) if misses < 3 => cluster.push(triangle),
+            _ => clusters.push(vec![triangle]),
+        }
+    }
+
+    let corners = |triangle: usize| {
+        [positions[indices[3 * triangle]], positions[indices[3 * triangle + 1]], positions[indices[3 * triangle + 2]]]
+    };
+    let centroid = |triangles: &[usize]| {
+        let sum = triangles.iter().fold([0_f32; 3], |sum, triangle| {
+            let [a, b, c] = corners(*triangle);
+            math::add(sum, math::add(a, math::add(b, c)))
+        });
+        math::scale(sum, 1_f32 / (3 * triangles.len()).max(1) as f32)
+    };
+    let all: Vec<usize> = (0..triangle_count).collect();
+    let center = centroid(&all);
+
+    let mut scored: Vec<(f32, Vec<usize>)> = clusters.into_iter().map(|cluster| {
+        let normal = cluster.iter().fold([0_f32; 3], |sum, triangle| {
+            let [a, b, c] = corners(*triangle);
+            math::add(sum, math::cross(math::sub(b, a), math::sub(c, a)))
+        });
+        let score = math::dot(math::sub(centroid(&cluster), center), math::normalize(normal));
+        (score, cluster)
+    }).collect();
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    scored.into_iter().flat_map(|(_, cluster)| cluster).collect()
+}
+
+/// Gather the triangles of an index buffer in the given order.
+fn reorder_triangles(indices: &[usize], order: &[usize]) -> Vec<usize> {
+    order.iter().flat_map(|triangle| indices[(3 * triangle)..(3 * triangle + 3)].iter().copied()).collect()
+}
+
+/// The number of vertices an index buffer refers to.
+fn vertex_count(indices: &[usize]) -> usize {
+    indices.iter().max().map_or(0, |max| max + 1)
+}
+
+/// Reorder the triangles of an index buffer so that they reuse the vertices
+/// in a post-transform vertex cache holding `cache_size` vertices. Each
+/// triangle keeps its winding and starting vertex. Indices after the last
+/// whole triangle are dropped.
+pub fn optimize_vertex_cache(indices: &[usize], cache_size: usize) -> Vec<usize> {
+    let indices = &indices[..(indices.len() - indices.len() % 3)];
+    let order = tipsify(indices, vertex_count(indices), cache_size);
+
+    reorder_triangles(indices, &order)
+}
+
+/// Reorder the triangles of an index buffer to reduce overdraw, keeping most
+/// of the vertex cache efficiency. The index buffer should already be
+/// optimized with `optimize_vertex_cache` for the same cache size; its
+/// triangles are split where the cache runs cold, and the pieces facing
+/// outward from the center of the mesh are moved to the front. `positions`
+/// holds the position of each vertex.
+pub fn optimize_overdraw(indices: &[usize], positions: &[[f32; 3]], cache_size: usize) -> Vec<usize> {
+    let indices = &indices[..(indices.len() - indices.len() % 3)];
+    let order = sort_clusters(indices, positions, cache_size);
+
+    reorder_triangles(indices, &order)
+}
+
+/// Renumber the vertices of an index buffer in the order the triangles first
+/// use them, so that the vertex buffer is read front to back. Returns the new
+/// number of each old vertex, or `usize::MAX` for vertices no triangle uses;
+/// reorder the vertex buffer to match with `remap_vertices`.
+pub fn optimize_vertex_fetch(indices: &mut [usize]) -> Vec<usize> {
+    let mut remap = vec![usize::MAX; vertex_count(indices)];
+    let mut next = 0;
+    for index in indices.iter_mut() {
+        if remap[*index] == usize::MAX {
+            remap[*index] = next;
+            next += 1;
+        }
+        *index = remap[*index];
+    }
+
+    remap
+}
+
+/// Reorder a vertex buffer according to a remapping table from
+/// `optimize_vertex_fetch`, leaving out the vertices no triangle uses.
+pub fn remap_vertices<T: Copy>(vertices: &[T], remap: &[usize]) -> Vec<T> {
+    let mut remapped = Vec::with_capacity(vertices.len());
+    for (vertex, new) in vertices.iter().zip(remap.iter()) {
+        if *new != usize::MAX {
+            if remapped.len() <= *new {
+                remapped.resize(*new + 1, *vertex);
+            }
+            remapped[*new] = *vertex;
+        }
+    }
+
+    remapped
+}
+
+impl ObjMesh {
+    /// Weld identical vertices, returning the index buffer of the mesh along
+    /// with the position of each distinct vertex.
+    fn index_buffer(&self) -> (Vec<usize>, Vec<[f32; 3]>) {
+        let report = self.clone().weld(&WeldOptions::default());
+        let mut positions = vec![[0_f32; 3]; report.unique];
+        for (point, index) in self.points().iter().zip(report.indices.iter()) {
+            positions[*index] = *point;
+        }
+
+        (report.indices, positions)
+    }
+
+    /// Rebuild the mesh with its triangles in the given order, keeping any
+    /// vertices after the last whole triangle at the end.
+    fn reorder(&mut self, order: &[usize]) {
+        let mut mesh = ObjMesh::with_capacity(self.len());
+        let vertices = order.iter().flat_map(|triangle| (3 * triangle)..(3 * triangle + 3));
+        for vertex in vertices.chain((3 * order.len())..self.len()) {
+            mesh.push_vertex_from(self, vertex);
+        }
+
+        *self = mesh;
+    }
+
+    /// Measure how well the mesh, drawn with identical vertices welded, uses
+    /// a FIFO vertex cache holding `cache_size` vertices.
+    pub fn cache_stats(&self, cache_size: usize) -> CacheStats {
+        cache_stats(&self.index_buffer().0, cache_size)
+    }
+
+    /// Reorder the triangles of the mesh for the post-transform vertex cache,
+    /// as `optimize_vertex_cache` does for an index buffer. Welding the result
+    /// numbers the vertices in the order they are first used, which is also
+    /// the best order for fetching them.
+    pub fn optimize_vertex_cache(&mut self, cache_size: usize) {
+        let (indices, _) = self.index_buffer();
+        let whole = indices.len() - indices.len() % 3;
+        let order = tipsify(&indices[..whole], vertex_count(&indices[..whole]), cache_size);
+        self.reorder(&order);
+    }
+
+    /// Reorder the triangles of the mesh for the vertex cache, then to reduce
+    /// overdraw, as `optimize_overdraw` does for an index buffer.
+    pub fn optimize_overdraw(&mut self, cache_size: usize) {
+        self.optimize_vertex_cache(cache_size);
+        let (indices, positions) = self.index_buffer();
+        let whole = indices.len() - indices.len() % 3;
+        let order = sort_clusters(&indices[..whole], &positions, cache_size);
+        self.reorder(&order);
+    }
+}
+
+
+#[cfg(test)]
+mod optimize_tests {
+    use crate::ObjMesh;
+
+    /// A grid of `n` by `n` unit squares, with its triangles shuffled.
+    fn shuffled_grid(n: usize) -> (Vec<usize>, Vec<[f32; 3]>) {
+        let mut triangles = vec![];
+        for i in 0..n {
+            for j in 0..n {
+                let [a, b, c, d] = [i * (n + 1) + j, (i + 1) * (n + 1) + j, (i + 1) * (n + 1) + j + 1, i * (n + 1) + j + 1];
+                triangles.push([a, b, c]);
+                triangles.push([a, c, d]);
+            }
+        }
+        let count = triangles.len();
+        let indices = (0..count).flat_map(|i| triangles[(37 * i) % count].to_vec()).collect();
+        let positions = (0..((n + 1) * (n + 1))).map(|v| [(v / (n + 1)) as f32, (v % (n + 1)) as f32, 0.0]).collect();
+
+        (indices, positions)
+    }
+
+    fn sorted_triangles(indices: &[usize]) -> Vec<Vec<usize>> {
+        let mut triangles: Vec<Vec<usize>> = indices.chunks_exact(3).map(|t| t.to_vec()).collect();
+        triangles.sort();
+
+        triangles
+    }
+
+    #[test]
+    fn test_cache_stats() {
+        let stats = super::cache_stats(&[0, 1, 2, 2, 1, 3, 4, 5, 6], 4);
+
+        assert_eq!(stats.misses, 7);
+        assert_eq!(stats.acmr, 7.0 / 3.0);
+        assert_eq!(stats.atvr, 1.0);
+    }
+
+    #[test]
+    fn test_optimize_vertex_cache() {
+        let (indices, _) = shuffled_grid(16);
+        let optimized = super::optimize_vertex_cache(&indices, 16);
+        let before = super::cache_stats(&indices, 16);
+        let after = super::cache_stats(&optimized, 16);
+
+        assert_eq!(sorted_triangles(&optimized), sorted_triangles(&indices));
+        assert!(after.acmr < 0.8 * before.acmr);
+        assert!(after.acmr < 1.0);
+    }
+
+    #[test]
+    fn test_optimize_overdraw_keeps_triangles() {
+        let (indices, positions) = shuffled_grid(8);
+        let optimized = super::optimize_vertex_cache(&indices, 16);
+        let sorted = super::optimize_overdraw(&optimized, &positions, 16);
+
+        assert_eq!(sorted_triangles(&sorted), sorted_triangles(&indices));
+        assert!(super::cache_stats(&sorted, 16).acmr < super::cache_stats(&indices, 16).acmr);
+    }
+
+    #[test]
+    fn test_optimize_vertex_fetch() {
+        let mut indices = vec![5, 3, 1, 1, 3, 0];
+        let remap = super::optimize_vertex_fetch(&mut indices);
+        let vertices = super::remap_vertices(&['a', 'b', 'c', 'd', 'e', 'f'], &remap);
+
+        assert_eq!(indices, vec![0, 1, 2, 2, 1, 3]);
+        assert_eq!(remap, vec![3, 2, usize::MAX, 1, usize::MAX, 0]);
+        assert_eq!(vertices, vec!['f', 'd', 'b', 'a']);
+    }
+
+    #[test]
+    fn test_optimize_mesh() {
+        let (indices, positions) = shuffled_grid(16);
+        let mut mesh = ObjMesh::default();
+        for index in indices.iter() {
+            mesh.push_vertex(positions[*index], [0.0, 0.0], [0.0, 0.0, 1.0]);
+        }
+        mesh.push_vertex([9.0, 9.0, 9.0], [0.0, 0.0], [0.0, 0.0, 1.0]);
+        let before = mesh.cache_stats(16);
+        let mut optimized = mesh.clone();
+        optimized.optimize_vertex_cache(16);
+
+        assert!(optimized.cache_stats(16).acmr < before.acmr);
+        assert_eq!(optimized.len(), mesh.len());
+        assert_eq!(optimized.points().last(), mesh.points().last());
+
+        optimized.optimize_overdraw(16);
+        assert_eq!(optimized.len(), mesh.len());
+    }
+}
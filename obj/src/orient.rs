@@ -0,0 +1,234 @@
+//! Repair of triangle winding.
+//!
+//! Meshes assembled from several sources often mix clockwise and
+//! counterclockwise triangles, so back-face culling punches holes in them.
+//! `ObjMesh::orient` walks each connected piece of the mesh across its shared
+//! edges, flipping triangles until neighbors agree, then turns closed pieces
+//! so that they face outward.
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use crate::math;
+use crate::spatial;
+use crate::ObjMesh;
+
+
+/// The outcome of orienting a mesh.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OrientReport {
+    /// The number of edge-connected pieces of the mesh.
+    pub components: usize,
+    /// The triangles whose winding was reversed.
+    pub flipped_triangles: Vec<usize>,
+    /// The number of vertex normals that were reversed to face the same way
+    /// as their triangles.
+    pub flipped_normals: usize,
+    /// The number of shared edges along which the winding could not be made
+    /// consistent, as on a Moebius strip.
+    pub conflicts: usize,
+}
+
+/// A triangle using an edge, and whether it runs along the edge from the
+/// smaller vertex to the larger one.
+type EdgeUse = (usize, bool);
+
+impl ObjMesh {
+    /// Make the winding of the triangles consistent across each connected
+    /// piece of the mesh, treating vertices within `epsilon` of each other as
+    /// shared, and turn closed pieces so that their triangles face outward.
+    /// Each piece keeps the winding of its first triangle unless it is closed
+    /// and encloses a negative volume. Afterwards, vertex normals that point
+    /// away from the front of their triangles are reversed.
+    ///
+    /// Edges shared by more than two triangles do not join pieces, since the
+    /// triangles around them cannot all agree.
+    pub fn orient(&mut self, epsilon: f32) -> OrientReport {
+        let triangle_count = self.points().len() / 3;
+        let classes = spatial::position_classes(&self.points()[..(3 * triangle_count)], epsilon);
+
+        let mut edges: HashMap<(usize, usize), Vec<EdgeUse>> = HashMap::new();
+        for triangle in 0..triangle_count {
+            for k in 0..3 {
+                let a = classes[3 * triangle + k];
+                let b = classes[3 * triangle + (k + 1) % 3];
+                if a != b {
+                    let key = if a < b { (a, b) } else { (b, a) };
+                    edges.entry(key).or_default().push((triangle, a < b));
+                }
+            }
+        }
+
+        // Link each triangle to its neighbors across manifold edges, noting
+        // whether the neighbor must have the opposite flip to agree with it.
+        let mut neighbors: Vec<Vec<(usize, bool)>> = vec![vec![]; triangle_count];
+        let mut open = vec![false; triangle_count];
+        for shared in edges.values() {
+            match shared[..] {
+                [(a, a_forward), (b, b_forward)] => {
+                    neighbors[a].push((b, a_forward == b_forward));
+                    neighbors[b].push((a, a_forward == b_forward));
+                }
+                _ => {
+                    for (triangle, _) in shared.iter() {
+                        open[*triangle] = true;
+                    }
+                }
+            }
+        }
+
+        let mut report = OrientReport::default();
+        let mut flips = vec![false; triangle_count];
+        let mut visited = vec![false; triangle_count];
+        let mut queue = VecDeque::new();
+        for start in 0..triangle_count {
+            if visited[start] {
+                continue;
+            }
+
+            report.components += 1;
+            visited[start] = true;
+            queue.push_back(start);
+            let mut component = vec![];
+            let mut closed = true;
+            while let Some(triangle) = queue.pop_front() {
+                component.push(triangle);
+                closed &= !open[triangle] && neighbors[triangle].len() == 3;
+                for (neighbor, opposite) in neighbors[triangle].iter() {
+                    let flip = flips[triangle] ^ *opposite;
+                    if !visited[*neighbor] {
+                        visited[*neighbor] = true;
+                        flips[*neighbor] = flip;
+                        queue.push_back(*neighbor);
+                    } else if flips[*neighbor] != flip && *neighbor > triangle {
+                        report.conflicts += 1;
+                    }
+                }
+            }
+
+            if closed {
+                let volume: f32 = component.iter().map(|triangle| {
+                    let [a, b, c] = self.triangle_points(*triangle);
+                    let volume = math::dot(a, math::cross(b, c));
+                    if flips[*triangle] { -volume } else { volume }
+                }).sum();
+                if volume < 0_f32 {
+                    for triangle in component.iter() {
+                        flips[*triangle] = !flips[*triangle];
+                    }
+                }
+            }
+        }
+
+        for triangle in (0..triangle_count).filter(|triangle| flips[*triangle]) {
+            self.swap_vertices(3 * triangle + 1, 3 * triangle + 2);
+            report.flipped_triangles.push(triangle);
+        }
+
+        for triangle in 0..triangle_count {
+            let [a, b, c] = self.triangle_points(triangle);
+            let face_normal = math::cross(math::sub(b, a), math::sub(c, a));
+            for normal in self.normals.inner[(3 * triangle)..(3 * triangle + 3)].iter_mut() {
+                if math::dot(*normal, face_normal) < 0_f32 {
+                    *normal = math::scale(*normal, -1_f32);
+                    report.flipped_normals += 1;
+                }
+            }
+        }
+
+        report
+    }
+
+    fn triangle_points(&self, triangle: usize) -> [[f32; 3]; 3] {
+        let points = self.points();
+
+        [points[3 * triangle], points[3 * triangle + 1], points[3 * triangle + 2]]
+    }
+}
+
+
+#[cfg(test)]
+mod orient_tests {
+    use crate::ObjMesh;
+
+    fn cube() -> ObjMesh {
+        let vertices = [
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0],
+        ];
+        let triangles = [
+            [0, 2, 1], [0, 3, 2], [4, 5, 6], [4, 6, 7],
+            [0, 1, 5], [0, 5, 4], [1, 2, 6], [1, 6, 5],
+            [2, 3, 7], [2, 7, 6], [3, 0, 4], [3, 4, 7],
+        ];
+        let mut mesh = ObjMesh::default();
+        for triangle in triangles.iter() {
+            let [a, b, c] = [vertices[triangle[0]], vertices[triangle[1]], vertices[triangle[2]]];
+            let normal = crate::math::triangle_normal(a, b, c);
+            mesh.push_triangle([a, b, c], [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]], [normal; 3]);
+        }
+
+        mesh
+    }
+
+    /// Reverse some triangles along with their normals, as an exporter with
+    /// mixed winding would write them.
+    fn flip_triangles(mesh: &ObjMesh, triangles: &[usize]) -> ObjMesh {
+        let mut result = ObjMesh::default();
+        for triangle in 0..(mesh.len() / 3) {
+            let mut corners = [3 * triangle, 3 * triangle + 1, 3 * triangle + 2];
+            let flipped = triangles.contains(&triangle);
+            if flipped {
+                corners.swap(1, 2);
+            }
+            for corner in corners.iter() {
+                let [x, y, z] = mesh.normals()[*corner];
+                let normal = if flipped { [-x, -y, -z] } else { [x, y, z] };
+                result.push_vertex(mesh.points()[*corner], mesh.tex_coords()[*corner], normal);
+            }
+        }
+
+        result
+    }
+
+    #[test]
+    fn test_orient_consistent_mesh() {
+        let mut mesh = cube();
+        let report = mesh.orient(0.0);
+
+        assert_eq!(mesh, cube());
+        assert_eq!(report.components, 1);
+        assert!(report.flipped_triangles.is_empty());
+        assert_eq!(report.flipped_normals, 0);
+    }
+
+    #[test]
+    fn test_orient_mixed_winding() {
+        let mut mesh = flip_triangles(&cube(), &[3, 6, 7]);
+        let report = mesh.orient(0.0);
+
+        assert_eq!(mesh, cube());
+        assert_eq!(report.flipped_triangles, vec![3, 6, 7]);
+        assert_eq!(report.flipped_normals, 9);
+        assert_eq!(report.conflicts, 0);
+    }
+
+    #[test]
+    fn test_orient_inside_out_mesh() {
+        let mut mesh = cube();
+        mesh.flip_winding();
+        let report = mesh.orient(0.0);
+
+        assert_eq!(mesh.points(), cube().points());
+        assert_eq!(report.flipped_triangles.len(), 12);
+    }
+
+    #[test]
+    fn test_orient_open_mesh_keeps_first_triangle() {
+        let mut mesh = flip_triangles(&cube(), &[1]);
+        mesh.truncate(6);
+        let report = mesh.orient(0.0);
+
+        assert_eq!(report.flipped_triangles, vec![1]);
+        assert_eq!(mesh.points()[3..6], cube().points()[3..6]);
+    }
+}
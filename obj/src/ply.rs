@@ -0,0 +1,713 @@
+//! Loading and saving object meshes in the PLY (Stanford polygon) format.
+//!
+//! The ASCII, binary little-endian, and binary big-endian encodings are all
+//! supported. Vertex positions come from the `x y z` properties, normals from
+//! `nx ny nz`, texture coordinates from `u v` or `s t`, and vertex colors
+//! from `red green blue`. Integer colors are scaled into `[0, 1]` by the
+//! largest value of their type, so the usual `uchar` colors are divided by
+//! 255, while floating point colors are taken as they are. Every other
+//! property and element is read past and dropped. Polygon faces are split
+//! into triangle fans the same way the OBJ loader splits them.
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::{ObjMesh, ObjMeshError};
+
+
+/// The encoding of the body of a PLY file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+impl PlyFormat {
+    fn name(self) -> &'static str {
+        match self {
+            PlyFormat::Ascii => "ascii",
+            PlyFormat::BinaryLittleEndian => "binary_little_endian",
+            PlyFormat::BinaryBigEndian => "binary_big_endian",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<ScalarType> {
+        match name {
+            "char" | "int8" => Some(ScalarType::Int8),
+            "uchar" | "uint8" => Some(ScalarType::UInt8),
+            "short" | "int16" => Some(ScalarType::Int16),
+            "ushort" | "uint16" => Some(ScalarType::UInt16),
+            "int" | "int32" => Some(ScalarType::Int32),
+            "uint" | "uint32" => Some(ScalarType::UInt32),
+            "float" | "float32" => Some(ScalarType::Float32),
+            "double" | "float64" => Some(ScalarType::Float64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    /// The value that stands for full intensity in a color of this type.
+    fn color_scale(self) -> f64 {
+        match self {
+            ScalarType::Int8 => i8::MAX as f64,
+            ScalarType::UInt8 => u8::MAX as f64,
+            ScalarType::Int16 => i16::MAX as f64,
+            ScalarType::UInt16 => u16::MAX as f64,
+            ScalarType::Int32 => i32::MAX as f64,
+            ScalarType::UInt32 => u32::MAX as f64,
+            ScalarType::Float32 | ScalarType::Float64 => 1_f64,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PropertyType {
+    Scalar(ScalarType),
+    List(ScalarType, ScalarType),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Property {
+    name: String,
+    property_type: PropertyType,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct ElementDescriptor {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Header {
+    format: PlyFormat,
+    elements: Vec<ElementDescriptor>,
+}
+
+/// Split the header off the front of a PLY file, returning the parsed header
+/// and the remaining body bytes.
+fn parse_header(buffer: &[u8]) -> Result<(Header, &[u8]), String> {
+    let mut format = None;
+    let mut elements: Vec<ElementDescriptor> = vec![];
+    let mut offset = 0;
+    let mut line_number = 0;
+    loop {
+        let end = match buffer[offset..].iter().position(|b| *b == b'\n') {
+            Some(end) => offset + end,
+            None => return Err(String::from("ERROR: PLY header is missing `end_header`")),
+        };
+        let line = std::str::from_utf8(&buffer[offset..end]).map_err(|_| {
+            String::from("ERROR: PLY header is not valid ASCII")
+        })?;
+        offset = end + 1;
+        line_number += 1;
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if line_number == 1 {
+            if tokens != ["ply"] {
+                return Err(String::from("ERROR: not a PLY file"));
+            }
+            continue;
+        }
+
+        match tokens.first().copied() {
+            Some("format") => {
+                format = match tokens.get(1).copied() {
+                    Some("ascii") => Some(PlyFormat::Ascii),
+                    Some("binary_little_endian") => Some(PlyFormat::BinaryLittleEndian),
+                    Some("binary_big_endian") => Some(PlyFormat::BinaryBigEndian),
+                    _ => return Err(format!("ERROR: line {}: unknown PLY format", line_number)),
+                };
+            }
+            Some("element") => {
+                if tokens.len() != 3 {
+                    return Err(format!("ERROR: line {}: malformed element", line_number));
+                }
+                let count = tokens[2].parse::<usize>().map_err(|_| {
+                    format!("ERROR: line {}: invalid element count `{}`", line_number, tokens[2])
+                })?;
+                elements.push(ElementDescriptor {
+                    name: String::from(tokens[1]),
+                    count,
+                    properties: vec![],
+                });
+            }
+            Some("property") => {
+                let element = elements.last_mut().ok_or_else(|| {
+                    format!("ERROR: line {}: property declared before any element", line_number)
+                })?;
+                let scalar = |name: &str| ScalarType::parse(name).ok_or_else(|| {
+                    format!("ERROR: line {}: unknown property type `{}`", line_number, name)
+                });
+                let property = match tokens.as_slice() {
+                    ["property", "list", count_type, item_type, name] => Property {
+                        name: String::from(*name),
+                        property_type: PropertyType::List(scalar(count_type)?, scalar(item_type)?),
+                    },
+                    ["property", scalar_type, name] => Property {
+                        name: String::from(*name),
+                        property_type: PropertyType::Scalar(scalar(scalar_type)?),
+                    },
+                    _ => return Err(format!("ERROR: line {}: malformed property", line_number)),
+                };
+                element.properties.push(property);
+            }
+            Some("end_header") => break,
+            _ => {}
+        }
+    }
+
+    let format = format.ok_or_else(|| String::from("ERROR: PLY header is missing `format`"))?;
+    // No real file has more items in an element than bytes in its body. An
+    // element without properties reads no bytes at all, so a corrupt count
+    // would otherwise keep the reader spinning for as long as it says.
+    let body = &buffer[offset..];
+    if let Some(element) = elements.iter().find(|element| element.count > body.len()) {
+        return Err(format!("ERROR: PLY element `{}` has more items than the body has bytes", element.name));
+    }
+
+    Ok((Header { format, elements }, body))
+}
+
+/// A source of property values from the body of a PLY file.
+trait ValueReader {
+    fn read(&mut self, scalar_type: ScalarType) -> Result<f64, String>;
+}
+
+struct AsciiReader<'a> {
+    tokens: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> ValueReader for AsciiReader<'a> {
+    fn read(&mut self, _scalar_type: ScalarType) -> Result<f64, String> {
+        let token = self.tokens.next().ok_or_else(|| {
+            String::from("ERROR: unexpected end of PLY data")
+        })?;
+
+        token.parse::<f64>().map_err(|_| format!("ERROR: invalid PLY value `{}`", token))
+    }
+}
+
+struct BinaryReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    big_endian: bool,
+}
+
+impl<'a> ValueReader for BinaryReader<'a> {
+    fn read(&mut self, scalar_type: ScalarType) -> Result<f64, String> {
+        let size = scalar_type.size();
+        if self.offset + size > self.data.len() {
+            return Err(String::from("ERROR: unexpected end of PLY data"));
+        }
+
+        let mut bytes = [0_u8; 8];
+        bytes[..size].copy_from_slice(&self.data[self.offset..(self.offset + size)]);
+        if self.big_endian {
+            bytes[..size].reverse();
+        }
+        self.offset += size;
+
+        let value = match scalar_type {
+            ScalarType::Int8 => i8::from_le_bytes([bytes[0]]) as f64,
+            ScalarType::UInt8 => bytes[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::Int32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::UInt32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::Float64 => f64::from_le_bytes(bytes),
+        };
+
+        Ok(value)
+    }
+}
+
+/// The positions of the vertex properties we know how to map onto an
+/// `ObjMesh`, by property index.
+#[derive(Default)]
+struct VertexLayout {
+    position: [Option<usize>; 3],
+    normal: [Option<usize>; 3],
+    tex_coord: [Option<usize>; 2],
+    color: [Option<usize>; 3],
+}
+
+impl VertexLayout {
+    fn new(element: &ElementDescriptor) -> VertexLayout {
+        let mut layout = VertexLayout::default();
+        for (i, property) in element.properties.iter().enumerate() {
+            let slot = match property.name.as_str() {
+                "x" => &mut layout.position[0],
+                "y" => &mut layout.position[1],
+                "z" => &mut layout.position[2],
+                "nx" => &mut layout.normal[0],
+                "ny" => &mut layout.normal[1],
+                "nz" => &mut layout.normal[2],
+                "u" | "s" | "texture_u" | "texture_s" => &mut layout.tex_coord[0],
+                "v" | "t" | "texture_v" | "texture_t" => &mut layout.tex_coord[1],
+                "red" | "diffuse_red" => &mut layout.color[0],
+                "green" | "diffuse_green" => &mut layout.color[1],
+                "blue" | "diffuse_blue" => &mut layout.color[2],
+                _ => continue,
+            };
+            if slot.is_none() {
+                *slot = Some(i);
+            }
+        }
+
+        layout
+    }
+
+    /// Determine whether the element has all three color channels.
+    fn has_colors(&self) -> bool {
+        self.color.iter().all(|channel| channel.is_some())
+    }
+}
+
+/// Read the elements of a PLY body of `body_len` bytes. Every list item
+/// takes at least one byte, which bounds the length of any list.
+fn read_elements<V: ValueReader>(header: &Header, values: &mut V, body_len: usize) -> Result<ObjMesh, String> {
+    let mut vertex_points = vec![];
+    let mut vertex_tex_coords = vec![];
+    let mut vertex_normals = vec![];
+    let mut vertex_colors = vec![];
+    let mut has_colors = false;
+    let mut faces: Vec<Vec<usize>> = vec![];
+    let mut has_faces = false;
+    for element in header.elements.iter() {
+        let is_vertex = element.name == "vertex";
+        let is_face = element.name == "face";
+        has_faces |= is_face;
+        let layout = VertexLayout::new(element);
+        if is_vertex && layout.position.iter().any(|p| p.is_none()) {
+            return Err(String::from("ERROR: PLY vertex element is missing `x`, `y`, or `z`"));
+        }
+        has_colors |= is_vertex && layout.has_colors();
+        let color_scales: Vec<f64> = element.properties.iter().map(|property| match property.property_type {
+            PropertyType::Scalar(scalar_type) => scalar_type.color_scale(),
+            PropertyType::List(_, _) => 1_f64,
+        }).collect();
+
+        let mut row = vec![0_f64; element.properties.len()];
+        for _ in 0..element.count {
+            for (i, property) in element.properties.iter().enumerate() {
+                match property.property_type {
+                    PropertyType::Scalar(scalar_type) => {
+                        row[i] = values.read(scalar_type)?;
+                    }
+                    PropertyType::List(count_type, item_type) => {
+                        let count = values.read(count_type)?;
+                        if !(count >= 0_f64 && count.fract() == 0_f64 && count <= body_len as f64) {
+                            return Err(format!("ERROR: invalid PLY list length `{}`", count));
+                        }
+                        let count = count as usize;
+                        let is_indices = property.name == "vertex_indices" || property.name == "vertex_index";
+                        let mut face = vec![];
+                        for _ in 0..count {
+                            let index = values.read(item_type)?;
+                            if is_face && is_indices {
+                                if !(index >= 0_f64 && index.fract() == 0_f64) {
+                                    return Err(format!("ERROR: invalid PLY vertex index `{}`", index));
+                                }
+                                face.push(index as usize);
+                            }
+                        }
+                        if is_face && is_indices {
+                            faces.push(face);
+                        }
+                    }
+                }
+            }
+
+            if is_vertex {
+                let get = |slot: Option<usize>| slot.map_or(0_f32, |i| row[i] as f32);
+                vertex_points.push([get(layout.position[0]), get(layout.position[1]), get(layout.position[2])]);
+                vertex_normals.push([get(layout.normal[0]), get(layout.normal[1]), get(layout.normal[2])]);
+                vertex_tex_coords.push([get(layout.tex_coord[0]), get(layout.tex_coord[1])]);
+                if has_colors {
+                    let color = layout.color.map(|slot| slot.map_or(0_f32, |i| (row[i] / color_scales[i]) as f32));
+                    vertex_colors.push(color);
+                }
+            }
+        }
+    }
+
+    if !has_faces {
+        let mesh = ObjMesh::try_new(vertex_points, vertex_tex_coords, vertex_normals);
+        return with_colors(mesh, has_colors, vertex_colors);
+    }
+
+    let mut points = vec![];
+    let mut tex_coords = vec![];
+    let mut normals = vec![];
+    let mut colors = vec![];
+    for face in faces.iter() {
+        if let Some(index) = face.iter().find(|index| **index >= vertex_points.len()) {
+            return Err(format!("ERROR: PLY face refers to missing vertex {}", index));
+        }
+
+        for i in 1..face.len().saturating_sub(1) {
+            for index in [face[0], face[i], face[i + 1]].iter() {
+                points.push(vertex_points[*index]);
+                tex_coords.push(vertex_tex_coords[*index]);
+                normals.push(vertex_normals[*index]);
+                if has_colors {
+                    colors.push(vertex_colors[*index]);
+                }
+            }
+        }
+    }
+
+    with_colors(ObjMesh::try_new(points, tex_coords, normals), has_colors, colors)
+}
+
+/// Attach the vertex colors read from a PLY file to a freshly built mesh.
+fn with_colors(
+    mesh: Result<ObjMesh, ObjMeshError>,
+    has_colors: bool,
+    colors: Vec<[f32; 3]>,
+) -> Result<ObjMesh, String> {
+    let mesh = match has_colors {
+        true => mesh.and_then(|mesh| mesh.with_colors(colors)),
+        false => mesh,
+    };
+
+    mesh.map_err(|e| format!("ERROR: {}", e))
+}
+
+/// Parse a PLY file from a buffer. A file without a `face` element is read
+/// as a point cloud, producing one mesh vertex per PLY vertex.
+pub fn load_from_memory(buffer: &[u8]) -> Result<ObjMesh, String> {
+    let (header, body) = parse_header(buffer)?;
+    match header.format {
+        PlyFormat::Ascii => {
+            let text = std::str::from_utf8(body).map_err(|_| {
+                String::from("ERROR: ASCII PLY data is not valid UTF-8")
+            })?;
+            let mut values = AsciiReader { tokens: text.split_ascii_whitespace() };
+            read_elements(&header, &mut values, body.len())
+        }
+        PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+            let mut values = BinaryReader {
+                data: body,
+                offset: 0,
+                big_endian: header.format == PlyFormat::BinaryBigEndian,
+            };
+            read_elements(&header, &mut values, body.len())
+        }
+    }
+}
+
+/// Parse a PLY file from a reader.
+pub fn load<R: io::Read>(reader: &mut R) -> Result<ObjMesh, String> {
+    let mut buffer = vec![];
+    reader.read_to_end(&mut buffer).map_err(|e| format!("ERROR: {}", e))?;
+
+    load_from_memory(&buffer)
+}
+
+/// Load a PLY file from disk.
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<ObjMesh, String> {
+    let file = match File::open(path.as_ref()) {
+        Ok(handle) => handle,
+        Err(_) => {
+            return Err(format!("ERROR: file not found: {}", path.as_ref().display()));
+        }
+    };
+
+    let mut reader = BufReader::new(file);
+    load(&mut reader)
+}
+
+/// Write a mesh as a PLY file in the given encoding. Each mesh vertex becomes
+/// a PLY vertex with position, normal, and texture coordinate properties, and
+/// each consecutive run of three vertices becomes a triangular face. The
+/// vertex colors of a mesh that has them are written as `uchar` properties.
+pub fn write<W: io::Write>(writer: &mut W, mesh: &ObjMesh, format: PlyFormat) -> io::Result<()> {
+    let remainder = mesh.len() % 3;
+    if remainder != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("mesh has {} vertices, which is not a whole number of triangles", mesh.len()),
+        ));
+    }
+    if mesh.len() > u32::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("mesh has too many vertices for PLY: {}", mesh.len()),
+        ));
+    }
+
+    let points = mesh.points();
+    let tex_coords = mesh.tex_coords();
+    let normals = mesh.normals();
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format {} 1.0", format.name())?;
+    writeln!(writer, "comment generated by mini_obj")?;
+    writeln!(writer, "element vertex {}", mesh.len())?;
+    for name in ["x", "y", "z", "nx", "ny", "nz", "u", "v"].iter() {
+        writeln!(writer, "property float {}", name)?;
+    }
+    if mesh.colors().is_some() {
+        for name in ["red", "green", "blue"].iter() {
+            writeln!(writer, "property uchar {}", name)?;
+        }
+    }
+    writeln!(writer, "element face {}", mesh.len() / 3)?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    let vertex = |i: usize| {
+        let tex_coord = tex_coords[i];
+        let normal = normals[i];
+        [
+            points[i][0], points[i][1], points[i][2],
+            normal[0], normal[1], normal[2],
+            tex_coord[0], tex_coord[1],
+        ]
+    };
+    let color = |i: usize| {
+        mesh.colors().map(|colors| colors[i].map(|channel| (channel.clamp(0_f32, 1_f32) * 255_f32).round() as u8))
+    };
+
+    match format {
+        PlyFormat::Ascii => {
+            for i in 0..mesh.len() {
+                let values = vertex(i);
+                let mut line: Vec<String> = values.iter().map(|value| format!("{}", value)).collect();
+                if let Some(color) = color(i) {
+                    line.extend(color.iter().map(|channel| format!("{}", channel)));
+                }
+                writeln!(writer, "{}", line.join(" "))?;
+            }
+            for i in (0..mesh.len()).step_by(3) {
+                writeln!(writer, "3 {} {} {}", i, i + 1, i + 2)?;
+            }
+        }
+        PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+            let big_endian = format == PlyFormat::BinaryBigEndian;
+            let f32_bytes = |value: f32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+            let u32_bytes = |value: u32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+            for i in 0..mesh.len() {
+                for value in vertex(i).iter() {
+                    writer.write_all(&f32_bytes(*value))?;
+                }
+                if let Some(color) = color(i) {
+                    writer.write_all(&color)?;
+                }
+            }
+            for i in (0..mesh.len()).step_by(3) {
+                writer.write_all(&[3])?;
+                for index in i..(i + 3) {
+                    writer.write_all(&u32_bytes(index as u32))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod ply_tests {
+    use super::PlyFormat;
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+        ];
+        let tex_coords = vec![
+            [0.0, 0.0], [1.0, 0.0], [1.0, 1.0],
+            [0.0, 0.0], [1.0, 1.0], [0.0, 1.0],
+        ];
+        let normals = vec![[0.0, 0.0, 1.0]; 6];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    #[test]
+    fn test_parse_ascii_ply_quad() {
+        let ply_file = "\
+            ply\n\
+            format ascii 1.0\n\
+            comment a unit quad with colors\n\
+            element vertex 4\n\
+            property float x\n\
+            property float y\n\
+            property float z\n\
+            property float nx\n\
+            property float ny\n\
+            property float nz\n\
+            property float s\n\
+            property float t\n\
+            property uchar red\n\
+            property uchar green\n\
+            property uchar blue\n\
+            element face 1\n\
+            property list uchar int vertex_indices\n\
+            end_header\n\
+            0 0 0 0 0 1 0 0 255 0 0\n\
+            1 0 0 0 0 1 1 0 0 255 0\n\
+            1 1 0 0 0 1 1 1 0 0 255\n\
+            0 1 0 0 0 1 0 1 255 255 255\n\
+            4 0 1 2 3\n\
+        ";
+        let result = super::load_from_memory(ply_file.as_bytes()).unwrap();
+        let colors = vec![
+            [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0, 1.0],
+        ];
+        let expected = test_mesh().with_colors(colors).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_ply_point_cloud_with_float_colors() {
+        let ply_file = "ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\n\
+            property float z\nproperty float red\nproperty float green\nproperty float blue\nend_header\n\
+            0 0 0 0.25 0.5 0.75\n1 0 0 1 1 1\n";
+        let result = super::load_from_memory(ply_file.as_bytes()).unwrap();
+
+        assert_eq!(result.colors(), Some(&[[0.25, 0.5, 0.75], [1.0, 1.0, 1.0]][..]));
+    }
+
+    #[test]
+    fn test_parse_binary_ply_skips_unknown_elements() {
+        let mut ply_file = Vec::from(&b"\
+            ply\n\
+            format binary_big_endian 1.0\n\
+            element vertex 3\n\
+            property double x\n\
+            property double y\n\
+            property double z\n\
+            property ushort flags\n\
+            element edge 1\n\
+            property list uchar short vertex_pair\n\
+            element face 1\n\
+            property list uchar uint vertex_index\n\
+            end_header\n\
+        "[..]);
+        let vertices = [[0.0_f64, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]];
+        for vertex in vertices.iter() {
+            for component in vertex.iter() {
+                ply_file.extend_from_slice(&component.to_be_bytes());
+            }
+            ply_file.extend_from_slice(&7_u16.to_be_bytes());
+        }
+        ply_file.push(2);
+        ply_file.extend_from_slice(&0_i16.to_be_bytes());
+        ply_file.extend_from_slice(&1_i16.to_be_bytes());
+        ply_file.push(3);
+        for index in 0..3_u32 {
+            ply_file.extend_from_slice(&index.to_be_bytes());
+        }
+        let result = super::load_from_memory(&ply_file).unwrap();
+
+        assert_eq!(result.points(), &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]]);
+        assert_eq!(result.tex_coords(), &[[0.0, 0.0]; 3]);
+        assert_eq!(result.normals(), &[[0.0, 0.0, 0.0]; 3]);
+    }
+
+    #[test]
+    fn test_ply_round_trip() {
+        let expected = test_mesh();
+        for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian].iter() {
+            let mut buffer = vec![];
+            super::write(&mut buffer, &expected, *format).unwrap();
+            let result = super::load_from_memory(&buffer).unwrap();
+
+            assert_eq!(result, expected, "PLY format {:?} did not round trip", format);
+        }
+    }
+
+    #[test]
+    fn test_ply_round_trip_with_colors() {
+        let colors = vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 0.0, 0.0], [0.5, 0.2, 0.8]];
+        let expected = test_mesh().with_colors(colors).unwrap();
+        for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian].iter() {
+            let mut buffer = vec![];
+            super::write(&mut buffer, &expected, *format).unwrap();
+            let result = super::load_from_memory(&buffer).unwrap();
+
+            assert_eq!(result.points(), expected.points());
+            let result_colors = result.colors().unwrap();
+            for (result, expected) in result_colors.iter().zip(expected.colors().unwrap().iter()) {
+                for k in 0..3 {
+                    assert!((result[k] - expected[k]).abs() <= 0.5 / 255.0 + 1e-6, "PLY format {:?} changed a color", format);
+                }
+            }
+            assert_eq!(&result_colors[..5], &expected.colors().unwrap()[..5]);
+        }
+    }
+
+    #[test]
+    fn test_ply_face_with_missing_vertex_is_rejected() {
+        let ply_file = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\n\
+            property float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0\n3 0 1 2\n";
+
+        assert!(super::load_from_memory(ply_file.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_ply_with_absurd_list_length_is_rejected() {
+        let header = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\n\
+            property float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n";
+        for face in ["1e20 0 0 0", "-1 0", "2.5 0 0", "1000 0 0 0"].iter() {
+            let ply_file = format!("{}0 0 0\n{}\n", header, face);
+
+            assert!(super::load_from_memory(ply_file.as_bytes()).is_err(), "list `{}` was accepted", face);
+        }
+    }
+    #[test]
+    fn test_ply_with_invalid_vertex_index_is_rejected() {
+        let header = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+            property float z\nelement face 1\nproperty list uchar float vertex_indices\nend_header\n";
+        for face in ["3 0 1 -1", "3 0 1 1.5", "3 0 1 inf", "3 0 1 NaN"].iter() {
+            let ply_file = format!("{}0 0 0\n1 0 0\n0 1 0\n{}\n", header, face);
+            let result = super::load_from_memory(ply_file.as_bytes());
+
+            assert!(
+                matches!(&result, Err(error) if error.starts_with("ERROR: invalid PLY vertex index")),
+                "face `{}` was not rejected: {:?}", face, result
+            );
+        }
+    }
+    #[test]
+    fn test_ply_with_absurd_element_count_is_rejected() {
+        let ply_file = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\n\
+            property float z\nelement junk 18446744073709551615\nend_header\n0 0 0\n";
+
+        assert!(super::load_from_memory(ply_file.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,451 @@
+//! Quantization of object meshes into compact vertex formats.
+//!
+//! Full precision floats are more than most meshes need, and on bandwidth
+//! bound hardware the size of the vertex buffer sets the speed of drawing.
+//! `ObjMesh::quantize` packs each attribute into a smaller format:
+//!
+//! * Positions are mapped into `[-1, 1]` over the bounding box of the mesh,
+//!   and stored as normalized `i16` values or as half precision floats. The
+//!   box is returned as an offset and scale to undo the mapping, which can be
+//!   folded into the model matrix.
+//! * Normals are folded onto an octahedron and stored as two normalized `i8`
+//!   or `i16` values, following Cigolle et al., "A Survey of Efficient
+//!   Representations for Independent Unit Vectors".
+//! * Texture coordinates are mapped into `[0, 1]` over their bounding
+//!   rectangle and stored as normalized `u16` values.
+//! * Vertex colors are not quantized, so a decoded mesh has none.
+//!
+//! The encoder reports the largest error it introduced in each attribute,
+//! and `QuantizedMesh::decode` unpacks the formats again for testing.
+use crate::bounds::Aabb;
+use crate::math;
+use crate::{ObjMesh, ObjMeshError};
+
+
+/// The format of quantized positions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PositionFormat {
+    /// Three normalized signed 16-bit integers.
+    Snorm16,
+    /// Three IEEE 754 half precision floats.
+    Half,
+}
+
+/// The format of quantized normals.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NormalFormat {
+    /// Two normalized signed 8-bit integers holding octahedral coordinates.
+    Oct8,
+    /// Two normalized signed 16-bit integers holding octahedral coordinates.
+    Oct16,
+}
+
+/// Options for quantizing a mesh.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct QuantizeOptions {
+    pub position_format: PositionFormat,
+    pub normal_format: NormalFormat,
+}
+
+impl Default for QuantizeOptions {
+    /// Use 16-bit positions and 8-bit normals, eight and two bytes per vertex
+    /// with padding.
+    fn default() -> QuantizeOptions {
+        QuantizeOptions {
+            position_format: PositionFormat::Snorm16,
+            normal_format: NormalFormat::Oct8,
+        }
+    }
+}
+
+/// Quantized positions, mapped into `[-1, 1]` over the bounding box.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuantizedPositions {
+    Snorm16(Vec<[i16; 3]>),
+    /// Half precision floats stored as their bits.
+    Half(Vec<[u16; 3]>),
+}
+
+/// Quantized normals in octahedral coordinates.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuantizedNormals {
+    Oct8(Vec<[i8; 2]>),
+    Oct16(Vec<[i16; 2]>),
+}
+
+/// A mesh with quantized attributes. A decoded position is
+/// `position_offset + position_scale * p` for the normalized position `p`,
+/// and likewise for texture coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantizedMesh {
+    pub positions: QuantizedPositions,
+    pub position_offset: [f32; 3],
+    pub position_scale: [f32; 3],
+    pub tex_coords: Vec<[u16; 2]>,
+    pub tex_coord_offset: [f32; 2],
+    pub tex_coord_scale: [f32; 2],
+    pub normals: QuantizedNormals,
+}
+
+/// The largest error quantization introduced in each attribute.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct QuantizationError {
+    /// The largest distance between a position and its quantized value.
+    pub position: f32,
+    /// The largest distance between a texture coordinate and its quantized
+    /// value.
+    pub tex_coord: f32,
+    /// The largest angle in radians between the direction of a normal and
+    /// its quantized value. Normals of zero length are not counted.
+    pub normal: f32,
+}
+
+/// Convert a single precision float to the bits of the nearest half
+/// precision float, rounding ties to even. Values too large for a half
+/// become infinite.
+pub fn encode_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    let (half, remainder, halfway) = if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+        // The value is subnormal as a half.
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - half_exponent) as u32;
+        (mantissa >> shift, mantissa & ((1 << shift) - 1), 1 << (shift - 1))
+    } else {
+        (((half_exponent as u32) << 10) | (mantissa >> 13), mantissa & 0x1fff, 0x1000)
+    };
+
+    // Rounding up may carry into the exponent, which is still correct.
+    let half = if remainder > halfway || (remainder == halfway && half & 1 == 1) { half + 1 } else { half };
+
+    sign | half as u16
+}
+
+/// Convert the bits of a half precision float to a single precision float.
+pub fn decode_half(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x03ff) as u32;
+
+    match exponent {
+        0 => {
+            let value = mantissa as f32 * (2_f32).powi(-24);
+            if sign != 0 { -value } else { value }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
+
+/// The sign of a value, counting zero as positive.
+#[inline]
+fn sign_not_zero(value: f32) -> f32 {
+    if value >= 0_f32 { 1_f32 } else { -1_f32 }
+}
+
+/// Map a direction to octahedral coordinates in `[-1, 1]`. The zero vector
+/// maps to the center, which decodes to `+Z`.
+pub fn encode_octahedral(normal: [f32; 3]) -> [f32; 2] {
+    let length = normal[0].abs() + normal[1].abs() + normal[2].abs();
+    if length == 0_f32 {
+        return [0_f32, 0_f32];
+    }
+
+    let [x, y, z] = math::scale(normal, 1_f32 / length);
+    if z >= 0_f32 {
+        [x, y]
+    } else {
+        [(1_f32 - y.abs()) * sign_not_zero(x), (1_f32 - x.abs()) * sign_not_zero(y)]
+    }
+}
+
+/// Map octahedral coordinates back to a unit direction.
+pub fn decode_octahedral(encoded: [f32; 2]) -> [f32; 3] {
+    let [x, y] = encoded;
+    let z = 1_f32 - x.abs() - y.abs();
+    let (x, y) = if z >= 0_f32 {
+        (x, y)
+    } else {
+        ((1_f32 - y.abs()) * sign_not_zero(x), (1_f32 - x.abs()) * sign_not_zero(y))
+    };
+
+    math::normalize([x, y, z])
+}
+
+/// Quantize a value in `[-1, 1]` to a normalized signed integer with the
+/// given largest value.
+#[inline]
+fn encode_snorm(value: f32, max: f32) -> f32 {
+    (value.clamp(-1_f32, 1_f32) * max).round()
+}
+
+/// Recover a value in `[-1, 1]` from a normalized signed integer.
+#[inline]
+fn decode_snorm(value: f32, max: f32) -> f32 {
+    (value / max).max(-1_f32)
+}
+
+/// Find the offset and scale that map the values of one coordinate into
+/// `[-1, 1]`, given their bounds. A coordinate with no extent gets a scale of
+/// zero.
+fn coordinate_range(min: f32, max: f32) -> (f32, f32) {
+    let center = 0.5 * (min + max);
+    let scale = 0.5 * (max - min);
+
+    (center, if scale.is_finite() { scale } else { 0_f32 })
+}
+
+impl QuantizedMesh {
+    /// Decode the normalized positions of the mesh, before the offset and
+    /// scale are applied.
+    fn normalized_positions(&self) -> Vec<[f32; 3]> {
+        match &self.positions {
+            QuantizedPositions::Snorm16(positions) => positions.iter().map(|p| {
+                p.map(|x| decode_snorm(x as f32, i16::MAX as f32))
+            }).collect(),
+            QuantizedPositions::Half(positions) => positions.iter().map(|p| p.map(decode_half)).collect(),
+        }
+    }
+
+    /// Decode the positions of the mesh.
+    pub fn decode_positions(&self) -> Vec<[f32; 3]> {
+        self.normalized_positions().iter().map(|p| {
+            [0, 1, 2].map(|k| self.position_offset[k] + self.position_scale[k] * p[k])
+        }).collect()
+    }
+
+    /// Decode the texture coordinates of the mesh.
+    pub fn decode_tex_coords(&self) -> Vec<[f32; 2]> {
+        self.tex_coords.iter().map(|t| {
+            [0, 1].map(|k| self.tex_coord_offset[k] + self.tex_coord_scale[k] * (t[k] as f32 / u16::MAX as f32))
+        }).collect()
+    }
+
+    /// Decode the normals of the mesh.
+    pub fn decode_normals(&self) -> Vec<[f32; 3]> {
+        match &self.normals {
+            QuantizedNormals::Oct8(normals) => normals.iter().map(|n| {
+                decode_octahedral(n.map(|x| decode_snorm(x as f32, i8::MAX as f32)))
+            }).collect(),
+            QuantizedNormals::Oct16(normals) => normals.iter().map(|n| {
+                decode_octahedral(n.map(|x| decode_snorm(x as f32, i16::MAX as f32)))
+            }).collect(),
+        }
+    }
+
+    /// Decode the mesh back into full precision, or return an error when the
+    /// attribute buffers have different lengths, which can only happen when
+    /// they were changed after quantization.
+    pub fn decode(&self) -> Result<ObjMesh, ObjMeshError> {
+        ObjMesh::try_new(self.decode_positions(), self.decode_tex_coords(), self.decode_normals())
+    }
+}
+
+impl ObjMesh {
+    /// Quantize the attributes of the mesh into compact formats, and measure
+    /// the largest error introduced in each. Non-finite values do not survive
+    /// quantization.
+    pub fn quantize(&self, options: &QuantizeOptions) -> (QuantizedMesh, QuantizationError) {
+        let bounds = self.aabb().unwrap_or(Aabb { min: [0_f32; 3], max: [0_f32; 3] });
+        let ranges = [0, 1, 2].map(|k| coordinate_range(bounds.min[k], bounds.max[k]));
+        let position_offset = ranges.map(|(center, _)| center);
+        let position_scale = ranges.map(|(_, scale)| scale);
+        let normalized = self.points().iter().map(|p| {
+            [0, 1, 2].map(|k| if position_scale[k] > 0_f32 { (p[k] - position_offset[k]) / position_scale[k] } else { 0_f32 })
+        });
+        let positions = match options.position_format {
+            PositionFormat::Snorm16 => QuantizedPositions::Snorm16(normalized.map(|p| {
+                p.map(|x| encode_snorm(x, i16::MAX as f32) as i16)
+            }).collect()),
+            PositionFormat::Half => QuantizedPositions::Half(normalized.map(|p| p.map(encode_half)).collect()),
+        };
+
+        let mut tex_coord_min = [f32::INFINITY; 2];
+        let mut tex_coord_max = [f32::NEG_INFINITY; 2];
+        for tex_coord in self.tex_coords().iter() {
+            for k in 0..2 {
+                tex_coord_min[k] = tex_coord_min[k].min(tex_coord[k]);
+                tex_coord_max[k] = tex_coord_max[k].max(tex_coord[k]);
+            }
+        }
+        let tex_coord_offset = tex_coord_min.map(|min| if min.is_finite() { min } else { 0_f32 });
+        let tex_coord_scale = [0, 1].map(|k| {
+            let extent = tex_coord_max[k] - tex_coord_min[k];
+            if extent.is_finite() { extent } else { 0_f32 }
+        });
+        let tex_coords = self.tex_coords().iter().map(|t| {
+            [0, 1].map(|k| {
+                let unorm = if tex_coord_scale[k] > 0_f32 { (t[k] - tex_coord_offset[k]) / tex_coord_scale[k] } else { 0_f32 };
+                (unorm.clamp(0_f32, 1_f32) * u16::MAX as f32).round() as u16
+            })
+        }).collect();
+
+        let encoded = self.normals().iter().map(|n| encode_octahedral(*n));
+        let normals = match options.normal_format {
+            NormalFormat::Oct8 => QuantizedNormals::Oct8(encoded.map(|n| {
+                n.map(|x| encode_snorm(x, i8::MAX as f32) as i8)
+            }).collect()),
+            NormalFormat::Oct16 => QuantizedNormals::Oct16(encoded.map(|n| {
+                n.map(|x| encode_snorm(x, i16::MAX as f32) as i16)
+            }).collect()),
+        };
+
+        let quantized = QuantizedMesh {
+            positions,
+            position_offset,
+            position_scale,
+            tex_coords,
+            tex_coord_offset,
+            tex_coord_scale,
+            normals,
+        };
+
+        let mut error = QuantizationError::default();
+        for (point, decoded) in self.points().iter().zip(quantized.decode_positions().iter()) {
+            error.position = error.position.max(math::length(math::sub(*point, *decoded)));
+        }
+        for (tex_coord, decoded) in self.tex_coords().iter().zip(quantized.decode_tex_coords().iter()) {
+            let difference = [tex_coord[0] - decoded[0], tex_coord[1] - decoded[1]];
+            error.tex_coord = error.tex_coord.max((difference[0] * difference[0] + difference[1] * difference[1]).sqrt());
+        }
+        for (normal, decoded) in self.normals().iter().zip(quantized.decode_normals().iter()) {
+            if math::length(*normal) > 0_f32 {
+                // The arc tangent stays accurate for small angles, where the
+                // arc cosine of the dot product does not.
+                let normal = math::normalize(*normal);
+                let angle = math::length(math::cross(normal, *decoded)).atan2(math::dot(normal, *decoded));
+                error.normal = error.normal.max(angle);
+            }
+        }
+
+        (quantized, error)
+    }
+}
+
+
+#[cfg(test)]
+mod quantize_tests {
+    use super::{NormalFormat, PositionFormat, QuantizeOptions, QuantizedPositions};
+    use crate::{ObjMesh, ObjMeshError};
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![
+            [-1.0, 2.0, 0.5], [3.0, 2.5, 0.5], [0.25, 6.0, 0.5],
+            [1.0, 2.0, 0.5], [2.0, 4.0, 0.5], [0.1, 0.3, 0.5],
+        ];
+        let tex_coords = vec![
+            [0.0, 0.0], [1.0, 0.0], [0.5, 1.0],
+            [-0.5, 0.3], [1.5, 0.7], [0.2, 0.9],
+        ];
+        let normals = vec![
+            [0.0, 0.0, 1.0], [0.0, 0.0, -1.0], [1.0, 0.0, 0.0],
+            [0.6, -0.8, 0.0], [0.48, 0.6, -0.64], [0.0, 0.0, 0.0],
+        ];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    #[test]
+    fn test_half_floats() {
+        assert_eq!(super::encode_half(1.0), 0x3c00);
+        assert_eq!(super::encode_half(-2.0), 0xc000);
+        assert_eq!(super::encode_half(65504.0), 0x7bff);
+        assert_eq!(super::encode_half(1.0e6), 0x7c00);
+        assert_eq!(super::encode_half(5.960464e-8), 0x0001);
+        assert_eq!(super::encode_half(1.0e-9), 0x0000);
+        assert_eq!(super::encode_half(1.0 + 1.0 / 2048.0), 0x3c00);
+        assert!(super::decode_half(super::encode_half(f32::NAN)).is_nan());
+        for value in [0.1, -3.75, 1000.5, 6.1e-5, 1.0e-6].iter() {
+            let decoded = super::decode_half(super::encode_half(*value));
+            assert!((decoded - value).abs() <= (value.abs() / 1024.0).max(2.0_f32.powi(-25)), "{} decoded as {}", value, decoded);
+        }
+    }
+
+    #[test]
+    fn test_octahedral_normals() {
+        let normals = [
+            [0.0, 0.0, 1.0], [0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0],
+            [0.48, 0.6, -0.64], [-0.6, 0.0, -0.8],
+        ];
+        for normal in normals.iter() {
+            let decoded = super::decode_octahedral(super::encode_octahedral(*normal));
+            for k in 0..3 {
+                assert!((decoded[k] - normal[k]).abs() < 1e-6, "{:?} decoded as {:?}", normal, decoded);
+            }
+        }
+    }
+
+    #[test]
+    fn test_quantize_mesh() {
+        let mesh = test_mesh();
+        let (quantized, error) = mesh.quantize(&QuantizeOptions::default());
+        let decoded = quantized.decode().unwrap();
+
+        assert_eq!(quantized.position_offset[0], 1.0);
+        assert_eq!(quantized.position_scale[0], 2.0);
+        assert_eq!(quantized.position_scale[2], 0.0);
+        assert!(error.position < 1e-4);
+        assert!(error.tex_coord < 2e-5);
+        assert!(error.normal < 0.02);
+        for (point, decoded) in mesh.points().iter().zip(decoded.points().iter()) {
+            assert_eq!(point[2], decoded[2]);
+        }
+        assert_eq!(decoded.points()[0][0], -1.0);
+        assert_eq!(decoded.tex_coords()[4][0], 1.5);
+    }
+
+    #[test]
+    fn test_quantize_mesh_with_wide_formats() {
+        let mesh = test_mesh();
+        let options = QuantizeOptions { position_format: PositionFormat::Half, normal_format: NormalFormat::Oct16 };
+        let (quantized, error) = mesh.quantize(&options);
+        let (_, narrow_error) = mesh.quantize(&QuantizeOptions::default());
+
+        match quantized.positions {
+            QuantizedPositions::Half(ref positions) => assert_eq!(positions[0][0], 0xbc00),
+            _ => panic!("expected half precision positions"),
+        }
+        assert!(error.position < 2e-3);
+        assert!(error.normal < 1e-4);
+        assert!(error.normal < narrow_error.normal);
+        assert_eq!(quantized.decode().unwrap().len(), mesh.len());
+    }
+
+    #[test]
+    fn test_quantize_empty_mesh() {
+        let (quantized, error) = ObjMesh::default().quantize(&QuantizeOptions::default());
+
+        assert!(quantized.decode().unwrap().is_empty());
+        assert_eq!(error.position, 0.0);
+    }
+
+    #[test]
+    fn test_decode_rejects_mismatched_buffers() {
+        let mesh = test_mesh();
+        let (mut quantized, _) = mesh.quantize(&QuantizeOptions::default());
+        quantized.tex_coords.pop();
+        let expected = ObjMeshError::MismatchedBufferLengths {
+            points: mesh.len(),
+            tex_coords: mesh.len() - 1,
+            normals: mesh.len(),
+        };
+
+        assert_eq!(quantized.decode(), Err(expected));
+    }
+}
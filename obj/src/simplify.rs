@@ -0,0 +1,542 @@
+//! Simplification of object meshes with quadric error metrics.
+//!
+//! Simplification removes vertices one at a time by collapsing each onto a
+//! neighboring vertex, always choosing the collapse that moves the surface
+//! the least. How far the surface moves is measured with the quadric error
+//! metric of Garland and Heckbert: every vertex accumulates the planes of the
+//! triangles around it, and the error of a position is the sum of its
+//! squared distances to those planes.
+//!
+//! Since a vertex always collapses onto an existing vertex, the texture
+//! coordinates, normals, and colors of the surviving vertices are kept as they
+//! are.
+//! Vertices on the boundary of the mesh and on UV seams can be locked in
+//! place so that outlines and texture layouts survive simplification.
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+
+use crate::math;
+use crate::spatial;
+use crate::ObjMesh;
+
+
+/// Options for simplifying a mesh. Simplification stops when the mesh has
+/// at most `target_triangles` triangles, or when the next collapse would
+/// exceed `max_error`, whichever comes first.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SimplifyOptions {
+    /// The number of triangles to simplify down to.
+    pub target_triangles: usize,
+    /// The largest error a collapse may introduce, roughly a distance in the
+    /// units of the mesh.
+    pub max_error: f32,
+    /// The largest distance between two positions that are treated as the
+    /// same vertex.
+    pub epsilon: f32,
+    /// Keep the vertices on the boundary of the mesh in place.
+    pub preserve_boundaries: bool,
+    /// Keep the vertices where the texture coordinates or normals of the
+    /// triangles around a position differ in place.
+    pub preserve_seams: bool,
+}
+
+impl Default for SimplifyOptions {
+    /// Simplify as far as possible, keeping boundaries and seams in place.
+    fn default() -> SimplifyOptions {
+        SimplifyOptions {
+            target_triangles: 0,
+            max_error: f32::INFINITY,
+            epsilon: 0_f32,
+            preserve_boundaries: true,
+            preserve_seams: true,
+        }
+    }
+}
+
+/// The outcome of simplifying a mesh.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SimplifyReport {
+    /// The number of triangles before simplification.
+    pub triangles_before: usize,
+    /// The number of triangles after simplification.
+    pub triangles_after: usize,
+    /// The largest error introduced by a collapse.
+    pub error: f32,
+}
+
+/// The weight of the planes that hold unlocked boundary edges in place,
+/// relative to the planes of the triangles.
+const BOUNDARY_WEIGHT: f64 = 10_f64;
+
+/// A symmetric 4x4 matrix measuring the squared distance of a point to a set
+/// of planes, stored as its upper triangle.
+#[derive(Copy, Clone, Debug, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: [f32; 3], point: [f32; 3], weight: f64) -> Quadric {
+        let [a, b, c] = [normal[0] as f64, normal[1] as f64, normal[2] as f64];
+        let d = -(a * point[0] as f64 + b * point[1] as f64 + c * point[2] as f64);
+
+        Quadric([
+            a * a, a * b, a * c, a * d,
+            b * b, b * c, b * d,
+            c * c, c * d,
+            d * d,
+        ].map(|x| weight * x))
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (x, y) in self.0.iter_mut().zip(other.0.iter()) {
+            *x += *y;
+        }
+    }
+
+    fn error(&self, point: [f32; 3]) -> f64 {
+        let q = &self.0;
+        let [x, y, z] = [point[0] as f64, point[1] as f64, point[2] as f64];
+        let error = q[0] * x * x + 2_f64 * q[1] * x * y + 2_f64 * q[2] * x * z + 2_f64 * q[3] * x
+            + q[4] * y * y + 2_f64 * q[5] * y * z + 2_f64 * q[6] * y
+            + q[7] * z * z + 2_f64 * q[8] * z
+            + q[9];
+
+        error.max(0_f64)
+    }
+}
+
+/// A candidate collapse of the vertex `from` onto the vertex `to`. The
+/// candidate is stale once the version of `from` has moved on.
+#[derive(Copy, Clone, Debug)]
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    version: usize,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Collapse) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Collapse) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    /// Order collapses by decreasing cost, so the binary heap pops the
+    /// cheapest first.
+    fn cmp(&self, other: &Collapse) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+            .then_with(|| other.from.cmp(&self.from))
+    }
+}
+
+/// The texture coordinate, normal, and color shared by triangle corners.
+type Wedge = ([f32; 2], [f32; 3], Option<[f32; 3]>);
+
+/// An indexed working copy of a mesh. Triangle corners refer to welded
+/// positions, and to wedges holding their texture coordinates, normals, and
+/// colors.
+struct Simplifier {
+    positions: Vec<[f32; 3]>,
+    wedges: Vec<Wedge>,
+    triangles: Vec<[usize; 3]>,
+    corners: Vec<[usize; 3]>,
+    alive: Vec<bool>,
+    alive_count: usize,
+    vertex_triangles: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    boundary: Vec<bool>,
+    versions: Vec<usize>,
+    preserve_seams: bool,
+}
+
+impl Simplifier {
+    fn new(mesh: &ObjMesh, options: &SimplifyOptions) -> Simplifier {
+        let triangle_count = mesh.len() / 3;
+        let points = &mesh.points()[..(3 * triangle_count)];
+        let classes = spatial::position_classes(points, options.epsilon);
+
+        let mut ids = vec![usize::MAX; classes.len()];
+        let mut positions = vec![];
+        for (i, class) in classes.iter().enumerate() {
+            if *class == i {
+                ids[i] = positions.len();
+                positions.push(points[i]);
+            }
+        }
+
+        // Corners at the same position with the same attributes share a
+        // wedge; positions with more than one wedge lie on a seam.
+        let mut wedge_ids = HashMap::new();
+        let mut wedges = vec![];
+        let mut wedge_counts = vec![0; positions.len()];
+        let mut triangles = vec![];
+        let mut corners = vec![];
+        for triangle in 0..triangle_count {
+            let vertices = [0, 1, 2].map(|k| ids[classes[3 * triangle + k]]);
+            if vertices[0] == vertices[1] || vertices[1] == vertices[2] || vertices[2] == vertices[0] {
+                continue;
+            }
+
+            let wedge = [0, 1, 2].map(|k| {
+                let corner = 3 * triangle + k;
+                let (tex_coord, normal) = (mesh.tex_coords()[corner], mesh.normals()[corner]);
+                let color = mesh.colors().map(|colors| colors[corner]);
+                let key = (
+                    vertices[k],
+                    tex_coord.map(f32::to_bits),
+                    normal.map(f32::to_bits),
+                    color.map(|color| color.map(f32::to_bits)),
+                );
+                *wedge_ids.entry(key).or_insert_with(|| {
+                    wedges.push((tex_coord, normal, color));
+                    wedge_counts[vertices[k]] += 1;
+                    wedges.len() - 1
+                })
+            });
+            triangles.push(vertices);
+            corners.push(wedge);
+        }
+
+        let mut vertex_triangles = vec![vec![]; positions.len()];
+        let mut quadrics = vec![Quadric::default(); positions.len()];
+        let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (t, triangle) in triangles.iter().enumerate() {
+            let [a, b, c] = triangle.map(|v| positions[v]);
+            let quadric = Quadric::from_plane(math::triangle_normal(a, b, c), a, 1_f64);
+            for k in 0..3 {
+                vertex_triangles[triangle[k]].push(t);
+                quadrics[triangle[k]].add(&quadric);
+                let (u, v) = (triangle[k], triangle[(k + 1) % 3]);
+                edges.entry(if u < v { (u, v) } else { (v, u) }).or_default().push(t);
+            }
+        }
+
+        let mut locked: Vec<bool> = wedge_counts.iter().map(|count| options.preserve_seams && *count > 1).collect();
+        let mut boundary = vec![false; positions.len()];
+        for ((u, v), shared) in edges.iter() {
+            match shared[..] {
+                [t] => {
+                    boundary[*u] = true;
+                    boundary[*v] = true;
+                    locked[*u] |= options.preserve_boundaries;
+                    locked[*v] |= options.preserve_boundaries;
+
+                    // Hold the edge in place with a plane through it at right
+                    // angles to its triangle.
+                    let [a, b, c] = triangles[t].map(|v| positions[v]);
+                    let edge = math::sub(positions[*v], positions[*u]);
+                    let normal = math::normalize(math::cross(edge, math::triangle_normal(a, b, c)));
+                    let quadric = Quadric::from_plane(normal, positions[*u], BOUNDARY_WEIGHT);
+                    quadrics[*u].add(&quadric);
+                    quadrics[*v].add(&quadric);
+                }
+                [_, _] => {}
+                _ => {
+                    locked[*u] = true;
+                    locked[*v] = true;
+                }
+            }
+        }
+
+        let alive_count = triangles.len();
+        Simplifier {
+            versions: vec![0; positions.len()],
+            alive: vec![true; triangles.len()],
+            alive_count,
+            positions,
+            wedges,
+            triangles,
+            corners,
+            vertex_triangles,
+            quadrics,
+            locked,
+            boundary,
+            preserve_seams: options.preserve_seams,
+        }
+    }
+
+    fn triangles_of(&self, vertex: usize) -> impl Iterator<Item = usize> + '_ {
+        self.vertex_triangles[vertex].iter().copied().filter(move |t| self.alive[*t])
+    }
+
+    fn neighbors(&self, vertex: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self.triangles_of(vertex)
+            .flat_map(|t| self.triangles[t].iter().copied())
+            .filter(|v| *v != vertex)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+
+        neighbors
+    }
+
+    /// Check whether `from` can collapse onto `to` without changing the
+    /// topology of the mesh, folding a triangle over, or tearing a seam.
+    /// Returns the wedge the corners of `from` take on.
+    fn can_collapse(&self, from: usize, to: usize) -> Option<usize> {
+        if self.locked[from] {
+            return None;
+        }
+
+        let edge_triangles: Vec<usize> = self.triangles_of(from)
+            .filter(|t| self.triangles[*t].contains(&to))
+            .collect();
+        if edge_triangles.is_empty() || (self.boundary[from] && edge_triangles.len() != 1) {
+            return None;
+        }
+
+        let mut wedges = edge_triangles.iter().map(|t| {
+            let k = self.triangles[*t].iter().position(|v| *v == to).unwrap();
+            self.corners[*t][k]
+        });
+        let wedge = wedges.next().unwrap();
+        if self.preserve_seams && wedges.any(|other| other != wedge) {
+            return None;
+        }
+
+        // The only neighbors of both vertices may be the opposite corners of
+        // the triangles on the edge, or the collapse pinches the surface.
+        let to_neighbors = self.neighbors(to);
+        let shared = self.neighbors(from).iter().filter(|v| to_neighbors.contains(v)).count();
+        if shared != edge_triangles.len() {
+            return None;
+        }
+
+        for t in self.triangles_of(from).filter(|t| !edge_triangles.contains(t)) {
+            let before = self.triangles[t].map(|v| self.positions[v]);
+            let after = self.triangles[t].map(|v| self.positions[if v == from { to } else { v }]);
+            let normal_before = math::cross(math::sub(before[1], before[0]), math::sub(before[2], before[0]));
+            let normal_after = math::cross(math::sub(after[1], after[0]), math::sub(after[2], after[0]));
+            if math::dot(normal_before, normal_after) < 0_f32 || math::length(normal_after) == 0_f32 {
+                return None;
+            }
+        }
+
+        Some(wedge)
+    }
+
+    fn cost(&self, from: usize, to: usize) -> f64 {
+        let mut quadric = self.quadrics[from];
+        quadric.add(&self.quadrics[to]);
+
+        quadric.error(self.positions[to])
+    }
+
+    /// Find the cheapest valid collapse of a vertex.
+    fn best_collapse(&self, from: usize) -> Option<Collapse> {
+        if self.locked[from] {
+            return None;
+        }
+
+        self.neighbors(from).into_iter()
+            .filter(|to| self.can_collapse(from, *to).is_some())
+            .map(|to| Collapse { cost: self.cost(from, to), from, to, version: self.versions[from] })
+            // Collapses are ordered by decreasing cost, so the greatest is the cheapest.
+            .max()
+    }
+
+    fn collapse(&mut self, from: usize, to: usize, wedge: usize) {
+        let triangles: Vec<usize> = self.triangles_of(from).collect();
+        for t in triangles {
+            if self.triangles[t].contains(&to) {
+                self.alive[t] = false;
+                self.alive_count -= 1;
+            } else {
+                let k = self.triangles[t].iter().position(|v| *v == from).unwrap();
+                self.triangles[t][k] = to;
+                self.corners[t][k] = wedge;
+                self.vertex_triangles[to].push(t);
+            }
+        }
+
+        let quadric = self.quadrics[from];
+        self.quadrics[to].add(&quadric);
+        self.vertex_triangles[from].clear();
+        self.versions[from] += 1;
+    }
+
+    fn to_obj_mesh(&self) -> ObjMesh {
+        let mut mesh = ObjMesh::with_capacity(3 * self.alive_count);
+        for t in (0..self.triangles.len()).filter(|t| self.alive[*t]) {
+            for k in 0..3 {
+                let point = self.positions[self.triangles[t][k]];
+                match self.wedges[self.corners[t][k]] {
+                    (tex_coord, normal, Some(color)) => mesh.push_colored_vertex(point, tex_coord, normal, color),
+                    (tex_coord, normal, None) => mesh.push_vertex(point, tex_coord, normal),
+                }
+            }
+        }
+
+        mesh
+    }
+}
+
+impl ObjMesh {
+    /// Simplify the mesh by collapsing vertices onto their neighbors, cheapest
+    /// collapse first, until the mesh is small enough or the next collapse
+    /// would introduce too much error. Triangles that collapse onto fewer
+    /// than three vertices are dropped, as are vertices after the last whole
+    /// triangle. See `SimplifyOptions` for the details.
+    pub fn simplify(&mut self, options: &SimplifyOptions) -> SimplifyReport {
+        let triangles_before = self.len() / 3;
+        let mut simplifier = Simplifier::new(self, options);
+        let mut heap: BinaryHeap<Collapse> = (0..simplifier.positions.len())
+            .filter_map(|vertex| simplifier.best_collapse(vertex))
+            .collect();
+
+        let mut error = 0_f32;
+        while simplifier.alive_count > options.target_triangles {
+            let candidate = match heap.pop() {
+                Some(candidate) => candidate,
+                None => break,
+            };
+            if candidate.version != simplifier.versions[candidate.from] {
+                continue;
+            }
+            let wedge = match simplifier.can_collapse(candidate.from, candidate.to) {
+                Some(wedge) => wedge,
+                None => {
+                    simplifier.versions[candidate.from] += 1;
+                    heap.extend(simplifier.best_collapse(candidate.from));
+                    continue;
+                }
+            };
+            let candidate_error = candidate.cost.sqrt() as f32;
+            if candidate_error > options.max_error {
+                break;
+            }
+
+            simplifier.collapse(candidate.from, candidate.to, wedge);
+            error = error.max(candidate_error);
+            let mut affected = simplifier.neighbors(candidate.to);
+            affected.push(candidate.to);
+            for vertex in affected {
+                simplifier.versions[vertex] += 1;
+                heap.extend(simplifier.best_collapse(vertex));
+            }
+        }
+
+        *self = simplifier.to_obj_mesh();
+
+        SimplifyReport { triangles_before, triangles_after: simplifier.alive_count, error }
+    }
+
+    /// Produce a chain of levels of detail, one for each ratio of the number
+    /// of triangles in the mesh. Each level is simplified from the one before
+    /// it, so the ratios should decrease. The target triangle count in the
+    /// options is replaced by each ratio in turn.
+    pub fn lod_chain(&self, ratios: &[f32], options: &SimplifyOptions) -> Vec<ObjMesh> {
+        let triangle_count = self.len() / 3;
+        let mut lods: Vec<ObjMesh> = Vec::with_capacity(ratios.len());
+        for ratio in ratios.iter() {
+            let mut lod = lods.last().unwrap_or(self).clone();
+            let target_triangles = (ratio.clamp(0_f32, 1_f32) * triangle_count as f32).ceil() as usize;
+            lod.simplify(&SimplifyOptions { target_triangles, ..*options });
+            lods.push(lod);
+        }
+
+        lods
+    }
+}
+
+
+#[cfg(test)]
+mod simplify_tests {
+    use super::SimplifyOptions;
+    use crate::ObjMesh;
+
+    /// A flat grid of `n` by `n` unit squares in the plane `z = 0`. The
+    /// texture coordinates jump along the middle column when `seam` is set.
+    fn grid(n: usize, seam: bool) -> ObjMesh {
+        let mut mesh = ObjMesh::default();
+        for i in 0..n {
+            for j in 0..n {
+                let corners = [[i, j], [i + 1, j], [i + 1, j + 1], [i, j + 1]];
+                let points = corners.map(|[x, y]| [x as f32, y as f32, 0.0]);
+                let tex_coords = corners.map(|[x, y]| {
+                    let offset = if seam && i >= n / 2 { 1.0 } else { 0.0 };
+                    [x as f32 / n as f32 + offset, y as f32 / n as f32]
+                });
+                let normal = [0.0, 0.0, 1.0];
+                mesh.push_triangle([points[0], points[1], points[2]], [tex_coords[0], tex_coords[1], tex_coords[2]], [normal; 3]);
+                mesh.push_triangle([points[0], points[2], points[3]], [tex_coords[0], tex_coords[2], tex_coords[3]], [normal; 3]);
+            }
+        }
+
+        mesh
+    }
+
+    fn area(mesh: &ObjMesh) -> f32 {
+        mesh.points().chunks_exact(3).map(|t| {
+            let (u, v) = ([t[1][0] - t[0][0], t[1][1] - t[0][1]], [t[2][0] - t[0][0], t[2][1] - t[0][1]]);
+            0.5 * (u[0] * v[1] - u[1] * v[0])
+        }).sum()
+    }
+
+    #[test]
+    fn test_simplify_keeps_boundaries() {
+        let mut mesh = grid(4, false);
+        let report = mesh.simplify(&SimplifyOptions::default());
+
+        // Only the 16 boundary vertices remain, which take 14 triangles.
+        assert_eq!(report.triangles_before, 32);
+        assert_eq!(report.triangles_after, 14);
+        assert_eq!(mesh.len(), 3 * 14);
+        assert_eq!(report.error, 0.0);
+        assert_eq!(area(&mesh), 16.0);
+        assert!(mesh.points().iter().all(|p| p[0] == 0.0 || p[0] == 4.0 || p[1] == 0.0 || p[1] == 4.0));
+    }
+
+    #[test]
+    fn test_simplify_to_error_threshold() {
+        let mut mesh = grid(4, false);
+        let options = SimplifyOptions { max_error: 1e-3, preserve_boundaries: false, ..SimplifyOptions::default() };
+        let report = mesh.simplify(&options);
+
+        // The boundary collapses onto the corners of the square.
+        assert_eq!(report.triangles_after, 2);
+        assert_eq!(area(&mesh), 16.0);
+        assert!(mesh.points().iter().all(|p| (p[0] == 0.0 || p[0] == 4.0) && (p[1] == 0.0 || p[1] == 4.0)));
+    }
+
+    #[test]
+    fn test_simplify_keeps_seams() {
+        let mut mesh = grid(4, true);
+        mesh.simplify(&SimplifyOptions::default());
+
+        for y in 0..=4 {
+            assert!(mesh.points().contains(&[2.0, y as f32, 0.0]));
+        }
+        for (point, tex_coord) in mesh.points().iter().zip(mesh.tex_coords().iter()) {
+            if point[0] < 2.0 {
+                assert!(tex_coord[0] < 1.0);
+            } else if point[0] > 2.0 {
+                assert!(tex_coord[0] > 1.0);
+            }
+        }
+        assert_eq!(area(&mesh), 16.0);
+    }
+
+    #[test]
+    fn test_lod_chain() {
+        let mesh = grid(4, false);
+        let options = SimplifyOptions { preserve_boundaries: false, ..SimplifyOptions::default() };
+        let lods = mesh.lod_chain(&[1.0, 0.5, 0.25], &options);
+
+        assert_eq!(lods.len(), 3);
+        assert_eq!(lods[0], mesh);
+        assert!(lods[1].len() / 3 <= 16 && lods[1].len() / 3 >= 15);
+        assert!(lods[2].len() / 3 <= 8 && lods[2].len() / 3 >= 7);
+        assert_eq!(area(&lods[2]), 16.0);
+    }
+}
@@ -0,0 +1,149 @@
+//! A uniform grid for finding nearby points quickly.
+use std::collections::HashMap;
+
+use crate::math;
+
+
+/// A spatial hash of points that answers "which stored point lies within
+/// `epsilon` of this one" by checking only the neighboring grid cells.
+///
+/// With a radius of zero only identical points match, so the points are
+/// keyed on their exact bits instead, and every cell holds one position. A
+/// grid with any fixed cell size would pile the points of a finely
+/// tessellated mesh into a few cells and make every search linear.
+pub(crate) struct SpatialHash {
+    epsilon: f32,
+    cell_size: f32,
+    cells: HashMap<[i64; 3], Vec<usize>>,
+}
+
+impl SpatialHash {
+    /// Construct an empty spatial hash for the given search radius. A radius
+    /// of zero matches identical points only.
+    pub(crate) fn new(epsilon: f32) -> SpatialHash {
+        let epsilon = if epsilon.is_finite() { epsilon.max(0_f32) } else { 0_f32 };
+
+        SpatialHash { epsilon, cell_size: epsilon, cells: HashMap::new() }
+    }
+
+    fn cell(&self, point: [f32; 3]) -> [i64; 3] {
+        if self.epsilon == 0_f32 {
+            // Adding zero turns negative zero into positive zero, which it
+            // equals.
+            return point.map(|x| (x + 0_f32).to_bits() as i64);
+        }
+
+        [
+            (point[0] / self.cell_size).floor() as i64,
+            (point[1] / self.cell_size).floor() as i64,
+            (point[2] / self.cell_size).floor() as i64,
+        ]
+    }
+
+    /// Store a point under an id.
+    pub(crate) fn insert(&mut self, point: [f32; 3], id: usize) {
+        let cell = self.cell(point);
+        self.cells.entry(cell).or_default().push(id);
+    }
+
+    /// Find a stored id whose point, given by `position`, lies within
+    /// `epsilon` of `point` and which `accept` agrees to.
+    pub(crate) fn find<P, A>(&self, point: [f32; 3], position: P, mut accept: A) -> Option<usize>
+    where
+        P: Fn(usize) -> [f32; 3],
+        A: FnMut(usize) -> bool,
+    {
+        let center = self.cell(point);
+        let reach = if self.epsilon == 0_f32 { 0 } else { 1 };
+        for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                for dz in -reach..=reach {
+                    // Infinite and huge coordinates saturate at the edge of
+                    // the grid, which has no cells beyond it.
+                    let cell = match (center[0].checked_add(dx), center[1].checked_add(dy), center[2].checked_add(dz)) {
+                        (Some(x), Some(y), Some(z)) => [x, y, z],
+                        _ => continue,
+                    };
+                    if let Some(ids) = self.cells.get(&cell) {
+                        for &id in ids.iter() {
+                            let distance = math::length(math::sub(position(id), point));
+                            if distance <= self.epsilon && accept(id) {
+                                return Some(id);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Assign every point the index of an earlier representative point within
+/// `epsilon` of it, or its own index when there is none. Returns the
+/// representative of each point.
+pub(crate) fn position_classes(points: &[[f32; 3]], epsilon: f32) -> Vec<usize> {
+    let mut hash = SpatialHash::new(epsilon);
+    let mut classes = Vec::with_capacity(points.len());
+    for (i, point) in points.iter().enumerate() {
+        match hash.find(*point, |id| points[id], |_| true) {
+            Some(representative) => classes.push(representative),
+            None => {
+                hash.insert(*point, i);
+                classes.push(i);
+            }
+        }
+    }
+
+    classes
+}
+
+
+#[cfg(test)]
+mod spatial_tests {
+    /// The corners of the triangles of a flat grid of `n` by `n` squares,
+    /// six to a square, so that every interior position repeats six times.
+    fn grid_points(n: usize, spacing: f32) -> Vec<[f32; 3]> {
+        let mut points = Vec::with_capacity(6 * n * n);
+        for i in 0..n {
+            for j in 0..n {
+                let corner = |di: usize, dj: usize| [(i + di) as f32 * spacing, (j + dj) as f32 * spacing, 0.0];
+                points.extend_from_slice(&[corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 0), corner(1, 1), corner(0, 1)]);
+            }
+        }
+
+        points
+    }
+
+    fn class_count(classes: &[usize]) -> usize {
+        classes.iter().enumerate().filter(|(i, class)| *i == **class).count()
+    }
+
+    #[test]
+    fn test_position_classes_of_large_mesh() {
+        // A quadratic search takes minutes on this many points.
+        let n = 200;
+        let points = grid_points(n, 1.0 / n as f32);
+
+        assert_eq!(class_count(&super::position_classes(&points, 0.0)), (n + 1) * (n + 1));
+        assert_eq!(class_count(&super::position_classes(&points, 1e-6)), (n + 1) * (n + 1));
+    }
+
+    #[test]
+    fn test_position_classes_match_signed_zeros() {
+        let points = [[0.0, 0.0, 0.0], [-0.0, 0.0, -0.0], [f32::NAN, 0.0, 0.0], [f32::NAN, 0.0, 0.0]];
+
+        assert_eq!(super::position_classes(&points, 0.0), vec![0, 0, 2, 3]);
+    }
+
+    #[test]
+    fn test_position_classes_with_infinite_and_huge_coordinates() {
+        let points = [
+            [f32::INFINITY, 0.0, 0.0], [f32::INFINITY, 0.0, 0.0], [0.0, 0.0, 0.0], [1e30, 0.0, 0.0],
+            [f32::NEG_INFINITY, f32::NEG_INFINITY, 0.0], [0.0, 0.0, 0.0], [1e30, 0.0, 0.0],
+        ];
+
+        assert_eq!(super::position_classes(&points, 1e-3), vec![0, 1, 2, 3, 4, 2, 3]);
+    }
+}
@@ -0,0 +1,436 @@
+//! Loading and saving object meshes in the STL format.
+//!
+//! Both the ASCII and the binary variants of STL are supported. An STL file
+//! stores a soup of triangles with one normal vector per facet, so loading one
+//! produces an `ObjMesh` whose vertices each carry the normal of their facet
+//! and whose texture coordinates are all zero. STL has no vertex colors, so
+//! saving a mesh drops them.
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::math;
+use crate::ObjMesh;
+
+
+/// The length of the header at the start of a binary STL file.
+pub const BINARY_HEADER_LEN: usize = 80;
+
+/// The length of a single triangle record in a binary STL file.
+const BINARY_TRIANGLE_LEN: usize = 50;
+
+/// The parts of a binary STL file that do not map onto an `ObjMesh`. Keeping
+/// these around lets a mesh be loaded, modified, and saved again without
+/// losing the data that other tools stash in them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BinaryStlHeader {
+    /// The free-form 80 byte header.
+    pub header: [u8; BINARY_HEADER_LEN],
+    /// The attribute byte count of each triangle, in file order.
+    pub attributes: Vec<u16>,
+}
+
+impl BinaryStlHeader {
+    /// Construct a header whose free-form text is `text`, truncated to fit.
+    pub fn with_text(text: &str) -> BinaryStlHeader {
+        let mut header = [0_u8; BINARY_HEADER_LEN];
+        let bytes = text.as_bytes();
+        let len = usize::min(bytes.len(), BINARY_HEADER_LEN);
+        header[..len].copy_from_slice(&bytes[..len]);
+
+        BinaryStlHeader {
+            header,
+            attributes: vec![],
+        }
+    }
+}
+
+impl Default for BinaryStlHeader {
+    fn default() -> BinaryStlHeader {
+        BinaryStlHeader::with_text("")
+    }
+}
+
+/// Determine whether a buffer holds a binary STL file. A binary file is
+/// recognized by its length agreeing with the triangle count in its header,
+/// since exporters commonly start binary headers with `solid` too.
+fn is_binary(buffer: &[u8]) -> bool {
+    if buffer.len() < BINARY_HEADER_LEN + 4 {
+        return false;
+    }
+
+    let count = read_u32(&buffer[BINARY_HEADER_LEN..]) as usize;
+    let expected_len = count
+        .checked_mul(BINARY_TRIANGLE_LEN)
+        .and_then(|len| len.checked_add(BINARY_HEADER_LEN + 4));
+
+    let start = buffer.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(buffer.len());
+
+    expected_len == Some(buffer.len()) || !buffer[start..].starts_with(b"solid")
+}
+
+#[inline]
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[inline]
+fn read_f32(bytes: &[u8]) -> f32 {
+    f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[inline]
+fn read_vec3(bytes: &[u8]) -> [f32; 3] {
+    [read_f32(&bytes[0..4]), read_f32(&bytes[4..8]), read_f32(&bytes[8..12])]
+}
+
+/// Append a facet to the mesh buffers. When the stored facet normal is
+/// missing or degenerate, it is recomputed from the facet's vertices.
+fn push_facet(
+    normal: [f32; 3],
+    vertices: [[f32; 3]; 3],
+    points: &mut Vec<[f32; 3]>,
+    tex_coords: &mut Vec<[f32; 2]>,
+    normals: &mut Vec<[f32; 3]>) {
+
+    let normal = if math::length(normal) > 0_f32 && normal.iter().all(|n| n.is_finite()) {
+        normal
+    } else {
+        math::triangle_normal(vertices[0], vertices[1], vertices[2])
+    };
+
+    for vertex in vertices.iter() {
+        points.push(*vertex);
+        tex_coords.push([0_f32, 0_f32]);
+        normals.push(normal);
+    }
+}
+
+/// Parse a binary STL file, returning the mesh along with the header and
+/// per-triangle attribute byte counts.
+pub fn load_binary_from_memory(buffer: &[u8]) -> Result<(ObjMesh, BinaryStlHeader), String> {
+    if buffer.len() < BINARY_HEADER_LEN + 4 {
+        return Err(format!("ERROR: binary STL file is too short: {} bytes", buffer.len()));
+    }
+
+    let mut header = BinaryStlHeader::default();
+    header.header.copy_from_slice(&buffer[..BINARY_HEADER_LEN]);
+    let count = read_u32(&buffer[BINARY_HEADER_LEN..]) as usize;
+    let body = &buffer[(BINARY_HEADER_LEN + 4)..];
+    if body.len() / BINARY_TRIANGLE_LEN < count {
+        return Err(format!(
+            "ERROR: binary STL file declares {} triangles but only contains {}",
+            count, body.len() / BINARY_TRIANGLE_LEN
+        ));
+    }
+
+    let mut points = Vec::with_capacity(3 * count);
+    let mut tex_coords = Vec::with_capacity(3 * count);
+    let mut normals = Vec::with_capacity(3 * count);
+    header.attributes.reserve(count);
+    for record in body.chunks_exact(BINARY_TRIANGLE_LEN).take(count) {
+        let normal = read_vec3(&record[0..12]);
+        let vertices = [
+            read_vec3(&record[12..24]),
+            read_vec3(&record[24..36]),
+            read_vec3(&record[36..48]),
+        ];
+        push_facet(normal, vertices, &mut points, &mut tex_coords, &mut normals);
+        header.attributes.push(u16::from_le_bytes([record[48], record[49]]));
+    }
+
+    let mesh = ObjMesh::try_new(points, tex_coords, normals).map_err(|e| format!("ERROR: {}", e))?;
+
+    Ok((mesh, header))
+}
+
+fn parse_vec3<'a, I: Iterator<Item = &'a str>>(tokens: &mut I, line: usize) -> Result<[f32; 3], String> {
+    let mut vector = [0_f32; 3];
+    for component in vector.iter_mut() {
+        let token = tokens.next().ok_or_else(|| {
+            format!("ERROR: line {}: expected three coordinates", line)
+        })?;
+        *component = token.parse::<f32>().map_err(|_| {
+            format!("ERROR: line {}: invalid number `{}`", line, token)
+        })?;
+    }
+
+    Ok(vector)
+}
+
+/// Parse an ASCII STL file.
+pub fn load_ascii_from_memory(buffer: &[u8]) -> Result<ObjMesh, String> {
+    let text = std::str::from_utf8(buffer).map_err(|_| {
+        String::from("ERROR: ASCII STL file is not valid UTF-8")
+    })?;
+
+    let mut points = vec![];
+    let mut tex_coords = vec![];
+    let mut normals = vec![];
+    let mut normal = [0_f32; 3];
+    let mut facet = vec![];
+    let mut in_facet = false;
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("facet") => {
+                if in_facet {
+                    return Err(format!("ERROR: line {}: facet is missing `endfacet`", line_number));
+                }
+                in_facet = true;
+                facet.clear();
+                normal = match tokens.next() {
+                    Some("normal") => parse_vec3(&mut tokens, line_number)?,
+                    _ => [0_f32; 3],
+                };
+            }
+            Some("vertex") => {
+                if !in_facet {
+                    return Err(format!("ERROR: line {}: vertex outside of a facet", line_number));
+                }
+                facet.push(parse_vec3(&mut tokens, line_number)?);
+            }
+            Some("endfacet") => {
+                if facet.len() != 3 {
+                    return Err(format!(
+                        "ERROR: line {}: facet has {} vertices; expected 3", line_number, facet.len()
+                    ));
+                }
+                push_facet(normal, [facet[0], facet[1], facet[2]], &mut points, &mut tex_coords, &mut normals);
+                in_facet = false;
+            }
+            _ => {}
+        }
+    }
+
+    if in_facet {
+        return Err(String::from("ERROR: unexpected end of file inside a facet"));
+    }
+
+    ObjMesh::try_new(points, tex_coords, normals).map_err(|e| format!("ERROR: {}", e))
+}
+
+/// Parse an STL file from a buffer, detecting whether it is ASCII or binary.
+pub fn load_from_memory(buffer: &[u8]) -> Result<ObjMesh, String> {
+    if is_binary(buffer) {
+        load_binary_from_memory(buffer).map(|(mesh, _)| mesh)
+    } else {
+        load_ascii_from_memory(buffer)
+    }
+}
+
+/// Parse an STL file from a reader, detecting whether it is ASCII or binary.
+pub fn load<R: io::Read>(reader: &mut R) -> Result<ObjMesh, String> {
+    let mut buffer = vec![];
+    reader.read_to_end(&mut buffer).map_err(|e| format!("ERROR: {}", e))?;
+
+    load_from_memory(&buffer)
+}
+
+/// Load an STL file from disk, detecting whether it is ASCII or binary.
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<ObjMesh, String> {
+    let file = match File::open(path.as_ref()) {
+        Ok(handle) => handle,
+        Err(_) => {
+            return Err(format!("ERROR: file not found: {}", path.as_ref().display()));
+        }
+    };
+
+    let mut reader = BufReader::new(file);
+    load(&mut reader)
+}
+
+fn check_triangles(mesh: &ObjMesh) -> io::Result<()> {
+    let remainder = mesh.len() % 3;
+    if remainder != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("mesh has {} vertices, which is not a whole number of triangles", mesh.len()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Compute the facet normal for the triangle starting at vertex `i`. The
+/// vertex normals are averaged when the mesh has them, and the geometric
+/// normal is used otherwise.
+fn facet_normal(mesh: &ObjMesh, i: usize) -> [f32; 3] {
+    let points = mesh.points();
+    let normals = mesh.normals();
+    if normals.len() > i + 2 {
+        if normals[i] == normals[i + 1] && normals[i] == normals[i + 2] && math::length(normals[i]) > 0_f32 {
+            return normals[i];
+        }
+
+        let sum = math::add(math::add(normals[i], normals[i + 1]), normals[i + 2]);
+        if math::length(sum) > 0_f32 {
+            return math::normalize(sum);
+        }
+    }
+
+    math::triangle_normal(points[i], points[i + 1], points[i + 2])
+}
+
+/// Write a mesh as an ASCII STL file with the solid name `name`.
+pub fn write_ascii<W: io::Write>(writer: &mut W, mesh: &ObjMesh, name: &str) -> io::Result<()> {
+    check_triangles(mesh)?;
+
+    let points = mesh.points();
+    writeln!(writer, "solid {}", name)?;
+    for i in (0..mesh.len()).step_by(3) {
+        let normal = facet_normal(mesh, i);
+        writeln!(writer, "  facet normal {:e} {:e} {:e}", normal[0], normal[1], normal[2])?;
+        writeln!(writer, "    outer loop")?;
+        for point in points[i..(i + 3)].iter() {
+            writeln!(writer, "      vertex {:e} {:e} {:e}", point[0], point[1], point[2])?;
+        }
+        writeln!(writer, "    endloop")?;
+        writeln!(writer, "  endfacet")?;
+    }
+    writeln!(writer, "endsolid {}", name)
+}
+
+/// Write a mesh as a binary STL file with an empty header.
+pub fn write_binary<W: io::Write>(writer: &mut W, mesh: &ObjMesh) -> io::Result<()> {
+    write_binary_with_header(writer, mesh, &BinaryStlHeader::default())
+}
+
+/// Write a mesh as a binary STL file, reusing the header and attribute byte
+/// counts from `header`. Triangles without a stored attribute byte count are
+/// written with a count of zero.
+pub fn write_binary_with_header<W: io::Write>(
+    writer: &mut W, mesh: &ObjMesh, header: &BinaryStlHeader) -> io::Result<()> {
+
+    check_triangles(mesh)?;
+
+    let count = mesh.len() / 3;
+    if count > u32::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("mesh has too many triangles for binary STL: {}", count),
+        ));
+    }
+
+    let points = mesh.points();
+    writer.write_all(&header.header)?;
+    writer.write_all(&(count as u32).to_le_bytes())?;
+    let mut record = [0_u8; BINARY_TRIANGLE_LEN];
+    for triangle in 0..count {
+        let i = 3 * triangle;
+        let normal = facet_normal(mesh, i);
+        let vectors = [normal, points[i], points[i + 1], points[i + 2]];
+        for (j, vector) in vectors.iter().enumerate() {
+            for (k, component) in vector.iter().enumerate() {
+                let offset = 12 * j + 4 * k;
+                record[offset..(offset + 4)].copy_from_slice(&component.to_le_bytes());
+            }
+        }
+        let attribute = header.attributes.get(triangle).copied().unwrap_or(0);
+        record[48..50].copy_from_slice(&attribute.to_le_bytes());
+        writer.write_all(&record)?;
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod stl_tests {
+    use super::BinaryStlHeader;
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0],
+        ];
+        let tex_coords = vec![[0.0, 0.0]; 6];
+        let normals = vec![
+            [0.0,  0.0, 1.0], [0.0,  0.0, 1.0], [0.0,  0.0, 1.0],
+            [0.0, -1.0, 0.0], [0.0, -1.0, 0.0], [0.0, -1.0, 0.0],
+        ];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    #[test]
+    fn test_parse_ascii_stl() {
+        let stl_file = r"
+            solid test
+              facet normal 0 0 1
+                outer loop
+                  vertex 0 0 0
+                  vertex 1 0 0
+                  vertex 0 1 0
+                endloop
+              endfacet
+              facet normal 0 0 0
+                outer loop
+                  vertex 0 0 0
+                  vertex 1 0 0
+                  vertex 0 0 1
+                endloop
+              endfacet
+            endsolid test
+        ";
+        let result = super::load_from_memory(stl_file.as_bytes()).unwrap();
+        let expected = test_mesh();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_ascii_stl_round_trip() {
+        let expected = test_mesh();
+        let mut buffer = vec![];
+        super::write_ascii(&mut buffer, &expected, "test").unwrap();
+        let result = super::load_from_memory(&buffer).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_binary_stl_round_trip() {
+        let expected = test_mesh();
+        let mut buffer = vec![];
+        super::write_binary(&mut buffer, &expected).unwrap();
+        let result = super::load_from_memory(&buffer).unwrap();
+
+        assert_eq!(buffer.len(), 84 + 2 * 50);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_binary_stl_header_is_preserved() {
+        let mut expected = BinaryStlHeader::with_text("solid but actually binary");
+        expected.attributes = vec![0x7FFF, 0x0123];
+        let mut buffer = vec![];
+        super::write_binary_with_header(&mut buffer, &test_mesh(), &expected).unwrap();
+        let (mesh, result) = super::load_binary_from_memory(&buffer).unwrap();
+
+        assert_eq!(mesh, test_mesh());
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_binary_stl_starting_with_solid_is_detected() {
+        let mut buffer = vec![];
+        let header = BinaryStlHeader::with_text("solid exported");
+        super::write_binary_with_header(&mut buffer, &test_mesh(), &header).unwrap();
+        let result = super::load_from_memory(&buffer).unwrap();
+
+        assert_eq!(result, test_mesh());
+    }
+
+    #[test]
+    fn test_truncated_binary_stl_is_rejected() {
+        let mut buffer = vec![];
+        super::write_binary(&mut buffer, &test_mesh()).unwrap();
+        buffer.truncate(buffer.len() - 10);
+
+        assert!(super::load_binary_from_memory(&buffer).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+//! Topological analysis of object meshes.
+//!
+//! 3D printing and physics engines need closed, consistently oriented
+//! surfaces. `ObjMesh::topology` welds the mesh by position and reports its
+//! boundary loops, its non-manifold edges and vertices, the edges along which
+//! neighboring triangles disagree on winding, and the Euler characteristic
+//! and genus of the surface.
+use crate::halfedge::HalfEdgeMesh;
+use crate::union_find::UnionFind;
+use crate::ObjMesh;
+
+
+/// The topology of a welded triangle mesh. Vertex ids refer to the vertices
+/// of the `HalfEdgeMesh` the report was computed from; edges are pairs of
+/// vertex ids, smaller first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TopologyReport {
+    /// The number of vertices used by at least one triangle.
+    pub vertex_count: usize,
+    /// The number of undirected edges.
+    pub edge_count: usize,
+    /// The number of triangles.
+    pub face_count: usize,
+    /// The number of edge-connected pieces of the mesh.
+    pub components: usize,
+    /// The closed chains of boundary edges, as the vertices around each
+    /// chain. A chain that cannot be closed is still reported.
+    pub boundary_loops: Vec<Vec<usize>>,
+    /// The edges shared by more than two triangles.
+    pub non_manifold_edges: Vec<(usize, usize)>,
+    /// The vertices whose triangles do not form a single fan.
+    pub non_manifold_vertices: Vec<usize>,
+    /// The edges shared by two triangles that run along it in the same
+    /// direction, so the triangles have opposite windings.
+    pub inconsistent_edges: Vec<(usize, usize)>,
+}
+
+impl TopologyReport {
+    /// Compute the Euler characteristic `V - E + F` of the mesh.
+    pub fn euler_characteristic(&self) -> isize {
+        self.vertex_count as isize - self.edge_count as isize + self.face_count as isize
+    }
+
+    /// Compute the total genus of the mesh, the number of handles summed over
+    /// its components. The genus is only defined for manifold meshes with a
+    /// consistent winding, so it is `None` otherwise.
+    pub fn genus(&self) -> Option<usize> {
+        if !self.is_manifold() || !self.is_consistently_oriented() {
+            return None;
+        }
+
+        // Each component satisfies chi = 2 - 2g - b.
+        let twice_genus = 2 * self.components as isize
+            - self.euler_characteristic()
+            - self.boundary_loops.len() as isize;
+        if twice_genus < 0 || twice_genus % 2 != 0 {
+            None
+        } else {
+            Some(twice_genus as usize / 2)
+        }
+    }
+
+    /// Determine whether the mesh has no boundary edges.
+    pub fn is_closed(&self) -> bool {
+        self.boundary_loops.is_empty()
+    }
+
+    /// Determine whether every edge and vertex of the mesh is manifold.
+    pub fn is_manifold(&self) -> bool {
+        self.non_manifold_edges.is_empty() && self.non_manifold_vertices.is_empty()
+    }
+
+    /// Determine whether neighboring triangles agree on winding across every
+    /// shared edge.
+    pub fn is_consistently_oriented(&self) -> bool {
+        self.inconsistent_edges.is_empty()
+    }
+
+    /// Determine whether the mesh is closed and manifold, so that it bounds
+    /// a solid.
+    pub fn is_watertight(&self) -> bool {
+        self.is_closed() && self.is_manifold()
+    }
+}
+
+/// Walk the boundary edges of a mesh into loops. The walk ignores the
+/// direction of the edges so that a badly wound boundary still forms a loop.
+fn boundary_loops(mesh: &HalfEdgeMesh, boundary: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let mut incident = vec![vec![]; mesh.vertex_count()];
+    for (i, (a, b)) in boundary.iter().enumerate() {
+        incident[*a].push(i);
+        incident[*b].push(i);
+    }
+
+    let mut used = vec![false; boundary.len()];
+    let mut loops = vec![];
+    for start in 0..boundary.len() {
+        if used[start] {
+            continue;
+        }
+
+        used[start] = true;
+        let (first, mut current) = boundary[start];
+        let mut chain = vec![first];
+        while current != first {
+            chain.push(current);
+            let next = incident[current].iter().copied().find(|i| !used[*i]);
+            match next {
+                Some(i) => {
+                    used[i] = true;
+                    let (a, b) = boundary[i];
+                    current = if a == current { b } else { a };
+                }
+                None => break,
+            }
+        }
+        loops.push(chain);
+    }
+
+    loops
+}
+
+impl HalfEdgeMesh {
+    /// Analyze the topology of the mesh. Unlike the twin links, the analysis
+    /// does not depend on winding, so a badly wound manifold surface is
+    /// reported as manifold with inconsistent edges.
+    pub fn topology(&self) -> TopologyReport {
+        let half_edges = self.half_edges();
+        let mut edges: Vec<((usize, usize), &[usize])> = self.edges().collect();
+        edges.sort_unstable_by_key(|(edge, _)| *edge);
+
+        let mut boundary = vec![];
+        let mut non_manifold_edges = vec![];
+        let mut inconsistent_edges = vec![];
+        let mut pieces = UnionFind::new(self.vertex_count());
+        for (edge, shared) in edges.iter() {
+            pieces.union(edge.0, edge.1);
+            match shared {
+                [h] => boundary.push((half_edges[*h].vertex, self.target(*h))),
+                [a, b] if half_edges[*a].vertex == half_edges[*b].vertex => inconsistent_edges.push(*edge),
+                [_, _] => {}
+                _ => non_manifold_edges.push(*edge),
+            }
+        }
+
+        let mut vertex_count = 0;
+        let mut components = 0;
+        for vertex in (0..self.vertex_count()).filter(|v| !self.outgoing(*v).is_empty()) {
+            vertex_count += 1;
+            if pieces.find(vertex) == vertex {
+                components += 1;
+            }
+        }
+
+        TopologyReport {
+            vertex_count,
+            edge_count: edges.len(),
+            face_count: self.face_count(),
+            components,
+            boundary_loops: boundary_loops(self, &boundary),
+            non_manifold_edges,
+            non_manifold_vertices: self.non_manifold_vertices(),
+            inconsistent_edges,
+        }
+    }
+}
+
+impl ObjMesh {
+    /// Analyze the topology of the mesh, welding vertices whose positions lie
+    /// within `epsilon` of each other. Vertex ids in the report refer to the
+    /// vertices of `HalfEdgeMesh::from_obj_mesh(self, epsilon)`.
+    pub fn topology(&self, epsilon: f32) -> TopologyReport {
+        HalfEdgeMesh::from_obj_mesh(self, epsilon).topology()
+    }
+}
+
+
+#[cfg(test)]
+mod topology_tests {
+    use crate::ObjMesh;
+
+    fn mesh_from_triangles(vertices: &[[f32; 3]], triangles: &[[usize; 3]]) -> ObjMesh {
+        let mut mesh = ObjMesh::default();
+        for triangle in triangles.iter() {
+            for vertex in triangle.iter() {
+                mesh.push_vertex(vertices[*vertex], [0.0, 0.0], [0.0, 0.0, 0.0]);
+            }
+        }
+
+        mesh
+    }
+
+    /// A tetrahedron whose welded vertex ids swap vertices 1 and 2, since ids
+    /// are given in order of first use.
+    fn tetrahedron() -> ObjMesh {
+        let vertices = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        let triangles = [[0, 2, 1], [0, 1, 3], [1, 2, 3], [0, 3, 2]];
+
+        mesh_from_triangles(&vertices, &triangles)
+    }
+
+    /// A square torus made of a 4 by 4 grid of quads.
+    fn torus() -> ObjMesh {
+        let mut vertices = vec![];
+        for i in 0..4 {
+            let angle = i as f32 * std::f32::consts::FRAC_PI_2;
+            for j in 0..4 {
+                let tube = j as f32 * std::f32::consts::FRAC_PI_2;
+                let radius = 2.0 + tube.cos();
+                vertices.push([radius * angle.cos(), radius * angle.sin(), tube.sin()]);
+            }
+        }
+        let mut triangles = vec![];
+        for i in 0..4 {
+            for j in 0..4 {
+                let a = 4 * i + j;
+                let b = 4 * ((i + 1) % 4) + j;
+                let c = 4 * ((i + 1) % 4) + (j + 1) % 4;
+                let d = 4 * i + (j + 1) % 4;
+                triangles.push([a, b, c]);
+                triangles.push([a, c, d]);
+            }
+        }
+
+        mesh_from_triangles(&vertices, &triangles)
+    }
+
+    #[test]
+    fn test_closed_surfaces() {
+        let report = tetrahedron().topology(0.0);
+
+        assert_eq!((report.vertex_count, report.edge_count, report.face_count), (4, 6, 4));
+        assert_eq!(report.euler_characteristic(), 2);
+        assert_eq!(report.genus(), Some(0));
+        assert!(report.is_watertight());
+        assert!(report.is_consistently_oriented());
+
+        let report = torus().topology(0.0);
+
+        assert_eq!(report.euler_characteristic(), 0);
+        assert_eq!(report.genus(), Some(1));
+        assert!(report.is_watertight());
+    }
+
+    #[test]
+    fn test_open_surface() {
+        let mut mesh = tetrahedron();
+        mesh.truncate(9);
+        let report = mesh.topology(0.0);
+
+        assert!(!report.is_closed());
+        assert!(report.is_manifold());
+        assert_eq!(report.boundary_loops, vec![vec![0, 1, 3]]);
+        assert_eq!(report.euler_characteristic(), 1);
+        assert_eq!(report.genus(), Some(0));
+    }
+
+    #[test]
+    fn test_inconsistent_winding() {
+        let vertices = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        // The tetrahedron with its last triangle turned inside out.
+        let triangles = [[0, 2, 1], [0, 1, 3], [1, 2, 3], [0, 2, 3]];
+        let mesh = mesh_from_triangles(&vertices, &triangles);
+        let report = mesh.topology(0.0);
+
+        assert!(report.is_watertight());
+        assert!(!report.is_consistently_oriented());
+        assert_eq!(report.inconsistent_edges, vec![(0, 1), (0, 3), (1, 3)]);
+        assert_eq!(report.genus(), None);
+    }
+
+    #[test]
+    fn test_non_manifold_mesh() {
+        let vertices = [
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, -1.0, 0.0],
+            [0.0, 0.0, 1.0], [-1.0, 0.0, 0.0], [-1.0, 1.0, 0.0],
+        ];
+        // Three triangles on the edge (0, 1), and a bowtie at vertex 0.
+        let triangles = [[0, 1, 2], [1, 0, 3], [0, 1, 4], [0, 5, 6]];
+        let report = mesh_from_triangles(&vertices, &triangles).topology(0.0);
+
+        assert_eq!(report.non_manifold_edges, vec![(0, 1)]);
+        assert_eq!(report.non_manifold_vertices, vec![0, 1]);
+        assert_eq!(report.components, 1);
+        assert!(!report.is_manifold());
+        assert_eq!(report.genus(), None);
+    }
+
+    #[test]
+    fn test_topology_of_empty_mesh() {
+        let report = ObjMesh::default().topology(0.0);
+
+        assert_eq!(report.euler_characteristic(), 0);
+        assert_eq!(report.components, 0);
+        assert!(report.is_watertight());
+    }
+}
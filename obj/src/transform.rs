@@ -0,0 +1,268 @@
+//! Affine transformations of object meshes.
+//!
+//! Matrices are 4x4 and column-major, so `matrix[i]` is the `i`-th column.
+//! This is the layout of `mint::ColumnMatrix4` and of the column arrays that
+//! `glam`, `nalgebra`, and `cgmath` produce, and it is what OpenGL expects.
+//! The bottom row of a matrix is assumed to be `[0, 0, 0, 1]`.
+use crate::math;
+use crate::ObjMesh;
+
+
+/// The 4x4 identity matrix.
+pub const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Construct a matrix that translates by `offset`.
+pub fn translation(offset: [f32; 3]) -> [[f32; 4]; 4] {
+    let mut matrix = IDENTITY;
+    matrix[3] = [offset[0], offset[1], offset[2], 1.0];
+
+    matrix
+}
+
+/// Construct a matrix that scales by `factors` along each axis.
+pub fn scaling(factors: [f32; 3]) -> [[f32; 4]; 4] {
+    let mut matrix = IDENTITY;
+    for i in 0..3 {
+        matrix[i][i] = factors[i];
+    }
+
+    matrix
+}
+
+/// Construct a matrix that rotates counterclockwise by `angle` radians about
+/// `axis`, looking down the axis towards the origin.
+pub fn rotation(axis: [f32; 3], angle: f32) -> [[f32; 4]; 4] {
+    let [x, y, z] = math::normalize(axis);
+    let (sin, cos) = angle.sin_cos();
+    let t = 1_f32 - cos;
+
+    [
+        [t * x * x + cos,     t * x * y + sin * z, t * x * z - sin * y, 0.0],
+        [t * x * y - sin * z, t * y * y + cos,     t * y * z + sin * x, 0.0],
+        [t * x * z + sin * y, t * y * z - sin * x, t * z * z + cos,     0.0],
+        [0.0,                 0.0,                 0.0,                 1.0],
+    ]
+}
+
+/// Multiply two matrices, so that the product applies `rhs` first and then
+/// `lhs`.
+pub fn multiply(lhs: &[[f32; 4]; 4], rhs: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut product = [[0_f32; 4]; 4];
+    for (column, rhs_column) in product.iter_mut().zip(rhs.iter()) {
+        for (row, entry) in column.iter_mut().enumerate() {
+            *entry = (0..4).map(|k| lhs[k][row] * rhs_column[k]).sum();
+        }
+    }
+
+    product
+}
+
+/// Compute the inverse of an affine matrix, or `None` when its linear part is
+/// singular.
+pub fn inverse(matrix: &[[f32; 4]; 4]) -> Option<[[f32; 4]; 4]> {
+    let determinant = linear_determinant(matrix);
+    if determinant == 0_f32 || !determinant.is_finite() {
+        return None;
+    }
+
+    // The inverse of the linear part is the transposed cofactor matrix over
+    // the determinant.
+    let cofactors = linear_cofactors(matrix);
+    let mut inverse = IDENTITY;
+    for i in 0..3 {
+        for j in 0..3 {
+            inverse[i][j] = cofactors[j][i] / determinant;
+        }
+    }
+    let offset = transform_vector(&inverse, [matrix[3][0], matrix[3][1], matrix[3][2]]);
+    inverse[3] = [-offset[0], -offset[1], -offset[2], 1.0];
+
+    Some(inverse)
+}
+
+/// Compute the determinant of the upper left 3x3 block of a matrix.
+fn linear_determinant(matrix: &[[f32; 4]; 4]) -> f32 {
+    let columns = [
+        [matrix[0][0], matrix[0][1], matrix[0][2]],
+        [matrix[1][0], matrix[1][1], matrix[1][2]],
+        [matrix[2][0], matrix[2][1], matrix[2][2]],
+    ];
+
+    math::dot(columns[0], math::cross(columns[1], columns[2]))
+}
+
+/// Compute the cofactor matrix of the upper left 3x3 block of a matrix, in
+/// column-major order. It equals the inverse transpose scaled by the
+/// determinant, so it maps normal vectors even when the block is singular.
+fn linear_cofactors(matrix: &[[f32; 4]; 4]) -> [[f32; 3]; 3] {
+    let columns = [
+        [matrix[0][0], matrix[0][1], matrix[0][2]],
+        [matrix[1][0], matrix[1][1], matrix[1][2]],
+        [matrix[2][0], matrix[2][1], matrix[2][2]],
+    ];
+
+    [
+        math::cross(columns[1], columns[2]),
+        math::cross(columns[2], columns[0]),
+        math::cross(columns[0], columns[1]),
+    ]
+}
+
+#[inline]
+fn transform_vector(matrix: &[[f32; 4]; 4], v: [f32; 3]) -> [f32; 3] {
+    [
+        matrix[0][0] * v[0] + matrix[1][0] * v[1] + matrix[2][0] * v[2],
+        matrix[0][1] * v[0] + matrix[1][1] * v[1] + matrix[2][1] * v[2],
+        matrix[0][2] * v[0] + matrix[1][2] * v[1] + matrix[2][2] * v[2],
+    ]
+}
+
+#[inline]
+fn transform_point(matrix: &[[f32; 4]; 4], p: [f32; 3]) -> [f32; 3] {
+    math::add(transform_vector(matrix, p), [matrix[3][0], matrix[3][1], matrix[3][2]])
+}
+
+impl ObjMesh {
+    /// Transform the mesh by an affine matrix. Points are transformed by the
+    /// matrix itself and normals by its inverse transpose, after which they
+    /// are renormalized. When the matrix mirrors the mesh, which is when its
+    /// determinant is negative, the winding of every triangle is reversed so
+    /// that front faces stay front faces.
+    pub fn transform(&mut self, matrix: &[[f32; 4]; 4]) {
+        for point in self.points.inner.iter_mut() {
+            *point = transform_point(matrix, *point);
+        }
+
+        let determinant = linear_determinant(matrix);
+        let cofactors = linear_cofactors(matrix);
+        let sign = if determinant < 0_f32 { -1_f32 } else { 1_f32 };
+        for normal in self.normals.inner.iter_mut() {
+            let transformed = math::add(
+                math::add(math::scale(cofactors[0], normal[0]), math::scale(cofactors[1], normal[1])),
+                math::scale(cofactors[2], normal[2]),
+            );
+            *normal = math::normalize(math::scale(transformed, sign));
+        }
+
+        if determinant < 0_f32 {
+            self.flip_winding();
+        }
+    }
+
+    /// Translate the mesh by `offset`.
+    pub fn translate(&mut self, offset: [f32; 3]) {
+        self.transform(&translation(offset));
+    }
+
+    /// Scale the mesh about the origin by `factors` along each axis.
+    pub fn scale(&mut self, factors: [f32; 3]) {
+        self.transform(&scaling(factors));
+    }
+
+    /// Rotate the mesh about the origin by `angle` radians about `axis`.
+    pub fn rotate(&mut self, axis: [f32; 3], angle: f32) {
+        self.transform(&rotation(axis, angle));
+    }
+
+    /// Reverse the winding of every triangle by swapping its second and third
+    /// vertices in every buffer.
+    pub fn flip_winding(&mut self) {
+        fn swap<T>(buffer: &mut [T]) {
+            for triangle in buffer.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+
+        swap(&mut self.points.inner);
+        swap(&mut self.tex_coords.inner);
+        swap(&mut self.normals.inner);
+        if let Some(colors) = &mut self.colors {
+            swap(&mut colors.inner);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod transform_tests {
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let tex_coords = vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+        let normals = vec![[0.0, 0.0, 1.0]; 3];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    fn assert_close(result: &[[f32; 3]], expected: &[[f32; 3]]) {
+        assert_eq!(result.len(), expected.len());
+        for (r, e) in result.iter().zip(expected.iter()) {
+            for i in 0..3 {
+                assert!((r[i] - e[i]).abs() < 1e-6, "expected {:?}, got {:?}", expected, result);
+            }
+        }
+    }
+
+    #[test]
+    fn test_translate() {
+        let mut mesh = test_mesh();
+        mesh.translate([1.0, 2.0, 3.0]);
+
+        assert_eq!(mesh.points(), &[[1.0, 2.0, 3.0], [2.0, 2.0, 3.0], [1.0, 3.0, 3.0]]);
+        assert_eq!(mesh.normals(), test_mesh().normals());
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut mesh = test_mesh();
+        mesh.rotate([0.0, 0.0, 1.0], std::f32::consts::FRAC_PI_2);
+        assert_close(mesh.points(), &[[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]]);
+
+        mesh.rotate([1.0, 0.0, 0.0], std::f32::consts::FRAC_PI_2);
+        assert_close(mesh.normals(), &[[0.0, -1.0, 0.0]; 3]);
+    }
+
+    #[test]
+    fn test_normals_use_inverse_transpose() {
+        let points = vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 0.0]];
+        let normal = [std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2, 0.0];
+        let mut mesh = ObjMesh::new(points, vec![[0.0, 0.0]; 3], vec![normal; 3]);
+        mesh.scale([2.0, 1.0, 1.0]);
+        let expected = [1.0 / 5_f32.sqrt(), 2.0 / 5_f32.sqrt(), 0.0];
+
+        assert_close(mesh.normals(), &[expected; 3]);
+    }
+
+    #[test]
+    fn test_mirroring_flips_winding() {
+        let mut mesh = test_mesh();
+        mesh.scale([-1.0, 1.0, 1.0]);
+
+        assert_eq!(mesh.points(), &[[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]]);
+        assert_eq!(mesh.tex_coords(), &[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0]]);
+        assert_eq!(mesh.normals(), &[[0.0, 0.0, 1.0]; 3]);
+    }
+
+    #[test]
+    fn test_inverse_undoes_transform() {
+        let matrix = super::multiply(
+            &super::translation([1.0, -2.0, 0.5]),
+            &super::multiply(&super::rotation([1.0, 1.0, 0.0], 0.7), &super::scaling([2.0, 3.0, -1.0])),
+        );
+        let inverse = super::inverse(&matrix).unwrap();
+        let mut mesh = test_mesh();
+        mesh.transform(&matrix);
+        mesh.transform(&inverse);
+        let expected = test_mesh();
+
+        assert_close(mesh.points(), expected.points());
+        assert_close(mesh.normals(), expected.normals());
+        assert!(super::inverse(&super::scaling([1.0, 0.0, 1.0])).is_none());
+    }
+}
@@ -0,0 +1,33 @@
+//! A union-find forest for grouping elements into disjoint sets.
+
+
+/// Disjoint sets over the elements `0..len`. Each set is represented by its
+/// smallest element.
+pub(crate) struct UnionFind {
+    parents: Vec<usize>,
+}
+
+impl UnionFind {
+    /// Construct a forest with every element in a set of its own.
+    pub(crate) fn new(len: usize) -> UnionFind {
+        UnionFind { parents: (0..len).collect() }
+    }
+
+    /// Find the representative of the set holding an element, compressing
+    /// the path along the way.
+    pub(crate) fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+
+        i
+    }
+
+    /// Merge the sets holding two elements.
+    pub(crate) fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+        self.parents[a.max(b)] = a.min(b);
+    }
+}
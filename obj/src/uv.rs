@@ -0,0 +1,159 @@
+//! Texture coordinate utilities for object meshes.
+//!
+//! Texture coordinates follow the OpenGL convention, with `v = 0` at the
+//! bottom of the image. Direct3D, Vulkan, and glTF put `v = 0` at the top, so
+//! texture coordinates moving between the two need `flip_v`.
+use crate::ObjMesh;
+
+
+/// A 2D transform of texture coordinates, applied as scale, then rotation,
+/// then offset. This matches the `KHR_texture_transform` glTF extension and
+/// the UV transforms of most material systems.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvTransform {
+    /// The offset added to the texture coordinates.
+    pub offset: [f32; 2],
+    /// The scale factor along each texture axis.
+    pub scale: [f32; 2],
+    /// The rotation in radians. As in `KHR_texture_transform`, a positive
+    /// angle turns `u = 1` towards `v = -1`.
+    pub rotation: f32,
+}
+
+impl UvTransform {
+    /// Apply the transform to a single texture coordinate.
+    pub fn apply(&self, tex_coord: [f32; 2]) -> [f32; 2] {
+        let u = tex_coord[0] * self.scale[0];
+        let v = tex_coord[1] * self.scale[1];
+        let (sin, cos) = self.rotation.sin_cos();
+
+        [cos * u + sin * v + self.offset[0], -sin * u + cos * v + self.offset[1]]
+    }
+}
+
+impl Default for UvTransform {
+    fn default() -> UvTransform {
+        UvTransform {
+            offset: [0_f32, 0_f32],
+            scale: [1_f32, 1_f32],
+            rotation: 0_f32,
+        }
+    }
+}
+
+impl ObjMesh {
+    /// Flip the texture coordinates vertically, replacing `v` with `1 - v`.
+    pub fn flip_v(&mut self) {
+        for tex_coord in self.tex_coords.inner.iter_mut() {
+            tex_coord[1] = 1_f32 - tex_coord[1];
+        }
+    }
+
+    /// Flip the texture coordinates horizontally, replacing `u` with `1 - u`.
+    pub fn flip_u(&mut self) {
+        for tex_coord in self.tex_coords.inner.iter_mut() {
+            tex_coord[0] = 1_f32 - tex_coord[0];
+        }
+    }
+
+    /// Move each triangle's texture coordinates by a whole number of tiles,
+    /// so that the lower corner of its UV bounds lies in `[0, 1)`. Sampling
+    /// with a repeating texture is unchanged. A triangle that crosses a tile
+    /// edge keeps its shape, so some of its coordinates can exceed one.
+    pub fn wrap_tex_coords(&mut self) {
+        for triangle in self.tex_coords.inner.chunks_mut(3) {
+            let mut min = [f32::INFINITY; 2];
+            for tex_coord in triangle.iter() {
+                min[0] = f32::min(min[0], tex_coord[0]);
+                min[1] = f32::min(min[1], tex_coord[1]);
+            }
+            let tile = [min[0].floor(), min[1].floor()];
+            if !tile[0].is_finite() || !tile[1].is_finite() {
+                continue;
+            }
+
+            for tex_coord in triangle.iter_mut() {
+                tex_coord[0] -= tile[0];
+                tex_coord[1] -= tile[1];
+            }
+        }
+    }
+
+    /// Clamp every texture coordinate into `[0, 1]`.
+    pub fn clamp_tex_coords(&mut self) {
+        for tex_coord in self.tex_coords.inner.iter_mut() {
+            tex_coord[0] = tex_coord[0].clamp(0_f32, 1_f32);
+            tex_coord[1] = tex_coord[1].clamp(0_f32, 1_f32);
+        }
+    }
+
+    /// Apply a 2D transform to every texture coordinate.
+    pub fn transform_tex_coords(&mut self, transform: &UvTransform) {
+        for tex_coord in self.tex_coords.inner.iter_mut() {
+            *tex_coord = transform.apply(*tex_coord);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod uv_tests {
+    use super::UvTransform;
+    use crate::ObjMesh;
+
+    fn test_mesh(tex_coords: Vec<[f32; 2]>) -> ObjMesh {
+        let len = tex_coords.len();
+
+        ObjMesh::new(vec![[0.0, 0.0, 0.0]; len], tex_coords, vec![[0.0, 0.0, 1.0]; len])
+    }
+
+    #[test]
+    fn test_flip() {
+        let mut mesh = test_mesh(vec![[0.25, 0.0], [1.0, 0.75], [0.5, 0.5]]);
+        mesh.flip_v();
+        assert_eq!(mesh.tex_coords(), &[[0.25, 1.0], [1.0, 0.25], [0.5, 0.5]]);
+
+        mesh.flip_u();
+        assert_eq!(mesh.tex_coords(), &[[0.75, 1.0], [0.0, 0.25], [0.5, 0.5]]);
+    }
+
+    #[test]
+    fn test_wrap_keeps_triangles_together() {
+        let mut mesh = test_mesh(vec![
+            [2.5, -0.5], [3.25, -0.5], [2.5, 0.25],
+            [-1.0, 1.0], [0.0, 1.0], [-1.0, 2.0],
+        ]);
+        mesh.wrap_tex_coords();
+        let expected = [
+            [0.5, 0.5], [1.25, 0.5], [0.5, 1.25],
+            [0.0, 0.0], [1.0, 0.0], [0.0, 1.0],
+        ];
+
+        assert_eq!(mesh.tex_coords(), &expected);
+    }
+
+    #[test]
+    fn test_clamp() {
+        let mut mesh = test_mesh(vec![[-0.5, 0.5], [1.5, 2.0], [0.25, -3.0]]);
+        mesh.clamp_tex_coords();
+
+        assert_eq!(mesh.tex_coords(), &[[0.0, 0.5], [1.0, 1.0], [0.25, 0.0]]);
+    }
+
+    #[test]
+    fn test_uv_transform() {
+        let transform = UvTransform {
+            offset: [0.5, 0.0],
+            scale: [2.0, 1.0],
+            rotation: std::f32::consts::FRAC_PI_2,
+        };
+        let mut mesh = test_mesh(vec![[1.0, 0.0], [0.0, 1.0], [0.0, 0.0]]);
+        mesh.transform_tex_coords(&transform);
+        let expected = [[0.5, -2.0], [1.5, 0.0], [0.5, 0.0]];
+
+        for (result, expected) in mesh.tex_coords().iter().zip(expected.iter()) {
+            assert!((result[0] - expected[0]).abs() < 1e-6 && (result[1] - expected[1]).abs() < 1e-6);
+        }
+        assert_eq!(UvTransform::default().apply([0.3, 0.7]), [0.3, 0.7]);
+    }
+}
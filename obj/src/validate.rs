@@ -0,0 +1,239 @@
+//! Validation of object meshes.
+//!
+//! `ObjMesh::validate` checks a mesh for the problems that break rendering or
+//! later processing, and reports each kind of problem once, together with
+//! the triangles it affects.
+use crate::interleave::Attribute;
+use crate::math;
+use crate::ObjMesh;
+
+
+/// How serious a validation issue is.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth knowing about, but often intended.
+    Info,
+    /// Likely to render incorrectly.
+    Warning,
+    /// The mesh cannot be used safely.
+    Error,
+}
+
+/// A kind of problem found in a mesh.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IssueKind {
+    /// The number of vertices is not a multiple of three, so the last
+    /// vertices do not form a triangle.
+    IncompleteTriangle {
+        trailing: usize,
+    },
+    /// An attribute holds a NaN or infinite value.
+    NonFiniteValue {
+        attribute: Attribute,
+    },
+    /// A triangle has zero area.
+    DegenerateTriangle,
+    /// A normal has zero length.
+    ZeroNormal,
+    /// A normal is not of unit length.
+    NonUnitNormal,
+    /// A texture coordinate lies outside `[0, 1]`.
+    TexCoordOutOfRange,
+}
+
+impl IssueKind {
+    /// Get the severity of the issue.
+    pub fn severity(&self) -> Severity {
+        match *self {
+            IssueKind::IncompleteTriangle { .. } => Severity::Error,
+            IssueKind::NonFiniteValue { .. } => Severity::Error,
+            IssueKind::DegenerateTriangle => Severity::Warning,
+            IssueKind::ZeroNormal => Severity::Warning,
+            IssueKind::NonUnitNormal => Severity::Warning,
+            IssueKind::TexCoordOutOfRange => Severity::Info,
+        }
+    }
+}
+
+/// A problem found in a mesh, with the triangles it affects. The triangles
+/// are empty for problems with the mesh as a whole.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub kind: IssueKind,
+    pub severity: Severity,
+    pub triangles: Vec<usize>,
+}
+
+/// The problems found in a mesh.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Determine whether the mesh has no problems of `Severity::Error`.
+    pub fn is_valid(&self) -> bool {
+        self.issues.iter().all(|issue| issue.severity < Severity::Error)
+    }
+
+    /// Find the issue of a given kind, if the mesh has it.
+    pub fn issue(&self, kind: IssueKind) -> Option<&ValidationIssue> {
+        self.issues.iter().find(|issue| issue.kind == kind)
+    }
+
+    /// Iterate over the issues of at least the given severity.
+    pub fn at_least(&self, severity: Severity) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(move |issue| issue.severity >= severity)
+    }
+
+    fn push(&mut self, kind: IssueKind, triangles: Vec<usize>) {
+        self.issues.push(ValidationIssue { kind, severity: kind.severity(), triangles });
+    }
+
+    fn push_triangles(&mut self, kind: IssueKind, mut triangles: Vec<usize>) {
+        if !triangles.is_empty() {
+            triangles.dedup();
+            self.push(kind, triangles);
+        }
+    }
+}
+
+/// The largest difference from one in the length of a normal that still
+/// counts as unit length.
+const NORMAL_LENGTH_TOLERANCE: f32 = 1e-3;
+
+/// Find the triangles holding a NaN or infinite value in a buffer.
+fn non_finite_triangles<T: AsRef<[f32]>>(values: &[T]) -> Vec<usize> {
+    values.iter().enumerate().filter(|(_, value)| {
+        !value.as_ref().iter().all(|x| x.is_finite())
+    }).map(|(i, _)| i / 3).collect()
+}
+
+impl ObjMesh {
+    /// Check the mesh for problems. See `IssueKind` for what is checked.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let points = self.points();
+        let tex_coords = self.tex_coords();
+        let normals = self.normals();
+
+        let trailing = points.len() % 3;
+        if trailing != 0 {
+            report.push(IssueKind::IncompleteTriangle { trailing }, vec![]);
+        }
+
+        let attributes = [
+            (Attribute::Position, non_finite_triangles(points)),
+            (Attribute::TexCoord, non_finite_triangles(tex_coords)),
+            (Attribute::Normal, non_finite_triangles(normals)),
+        ];
+        for (attribute, triangles) in attributes.iter() {
+            report.push_triangles(IssueKind::NonFiniteValue { attribute: *attribute }, triangles.clone());
+        }
+
+        let mut degenerate = vec![];
+        for (i, triangle) in points.chunks_exact(3).enumerate() {
+            let edges = [
+                math::sub(triangle[1], triangle[0]),
+                math::sub(triangle[2], triangle[0]),
+                math::sub(triangle[2], triangle[1]),
+            ];
+            let longest = edges.iter().map(|edge| math::length(*edge)).fold(0_f32, f32::max);
+            let area = math::length(math::cross(edges[0], edges[1]));
+            if area <= f32::EPSILON * longest * longest {
+                degenerate.push(i);
+            }
+        }
+        report.push_triangles(IssueKind::DegenerateTriangle, degenerate);
+
+        let mut zero_normals = vec![];
+        let mut non_unit_normals = vec![];
+        for (i, normal) in normals.iter().enumerate() {
+            let length = math::length(*normal);
+            if length == 0_f32 {
+                zero_normals.push(i / 3);
+            } else if (length - 1_f32).abs() > NORMAL_LENGTH_TOLERANCE {
+                non_unit_normals.push(i / 3);
+            }
+        }
+        report.push_triangles(IssueKind::ZeroNormal, zero_normals);
+        report.push_triangles(IssueKind::NonUnitNormal, non_unit_normals);
+
+        let out_of_range = tex_coords.iter().enumerate().filter(|(_, tex_coord)| {
+            tex_coord.iter().any(|x| *x < 0_f32 || *x > 1_f32)
+        }).map(|(i, _)| i / 3).collect();
+        report.push_triangles(IssueKind::TexCoordOutOfRange, out_of_range);
+
+        report
+    }
+}
+
+
+#[cfg(test)]
+mod validate_tests {
+    use super::{IssueKind, Severity};
+    use crate::interleave::Attribute;
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0],
+        ];
+        let tex_coords = vec![
+            [0.0, 0.0], [1.0, 0.0], [0.0, 1.0],
+            [0.0, 0.0], [1.5, 0.0], [0.0, 1.0],
+        ];
+        let normals = vec![
+            [0.0, 0.0, 1.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0],
+            [0.0, 0.0, 0.0], [0.0, 0.0, 2.0], [0.0, 0.0, 1.0],
+        ];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    #[test]
+    fn test_valid_mesh() {
+        let mesh = ObjMesh::new(
+            test_mesh().points()[..3].to_vec(),
+            test_mesh().tex_coords()[..3].to_vec(),
+            test_mesh().normals()[..3].to_vec(),
+        );
+        let report = mesh.validate();
+
+        assert!(report.issues.is_empty());
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn test_triangle_issues() {
+        let report = test_mesh().validate();
+
+        assert!(report.is_valid());
+        assert_eq!(report.issue(IssueKind::DegenerateTriangle).unwrap().triangles, vec![1]);
+        assert_eq!(report.issue(IssueKind::ZeroNormal).unwrap().triangles, vec![1]);
+        assert_eq!(report.issue(IssueKind::NonUnitNormal).unwrap().triangles, vec![1]);
+        let out_of_range = report.issue(IssueKind::TexCoordOutOfRange).unwrap();
+        assert_eq!(out_of_range.triangles, vec![1]);
+        assert_eq!(out_of_range.severity, Severity::Info);
+        assert_eq!(report.at_least(Severity::Warning).count(), 3);
+    }
+
+    #[test]
+    fn test_structural_errors() {
+        let mut points = test_mesh().points().to_vec();
+        points[2][1] = f32::NAN;
+        points.push([0.0, 0.0, 0.0]);
+        let mut tex_coords = test_mesh().tex_coords().to_vec();
+        tex_coords.push([0.0, 0.0]);
+        let mut normals = test_mesh().normals().to_vec();
+        normals.push([0.0, 0.0, 1.0]);
+        let mesh = ObjMesh::new(points, tex_coords, normals);
+        let report = mesh.validate();
+        let non_finite = IssueKind::NonFiniteValue { attribute: Attribute::Position };
+
+        assert!(!report.is_valid());
+        assert!(report.issue(IssueKind::IncompleteTriangle { trailing: 1 }).is_some());
+        assert_eq!(report.issue(non_finite).unwrap().triangles, vec![0]);
+    }
+}
@@ -0,0 +1,181 @@
+//! Welding of near-coincident vertices.
+//!
+//! The loaders flatten faces, so an `ObjMesh` repeats the vertices that its
+//! triangles share, and exporters often leave vertices a hair apart along
+//! seams. Welding snaps each vertex onto an earlier vertex close enough to
+//! it, and numbers the distinct vertices that remain so the mesh can be
+//! treated as indexed.
+use crate::math;
+use crate::spatial::SpatialHash;
+use crate::ObjMesh;
+
+
+/// Options for welding the vertices of a mesh.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WeldOptions {
+    /// The largest distance between two positions that are welded together.
+    pub epsilon: f32,
+    /// The largest distance between the texture coordinates of two welded
+    /// vertices, or `None` to weld regardless of texture coordinates.
+    pub tex_coord_epsilon: Option<f32>,
+    /// The largest distance between the normals of two welded vertices, or
+    /// `None` to weld regardless of normals.
+    pub normal_epsilon: Option<f32>,
+}
+
+impl Default for WeldOptions {
+    /// Weld vertices that are exactly identical.
+    fn default() -> WeldOptions {
+        WeldOptions {
+            epsilon: 0_f32,
+            tex_coord_epsilon: Some(0_f32),
+            normal_epsilon: Some(0_f32),
+        }
+    }
+}
+
+/// The outcome of welding a mesh.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WeldReport {
+    /// The number of vertices that were merged into an earlier vertex.
+    pub merged: usize,
+    /// The number of distinct vertices left after welding.
+    pub unique: usize,
+    /// The distinct vertex each vertex of the mesh was welded to, numbered
+    /// from zero in the order the distinct vertices first appear.
+    pub indices: Vec<usize>,
+}
+
+impl ObjMesh {
+    /// Weld vertices that lie within the tolerances of `options` of an
+    /// earlier vertex. A welded vertex takes on every attribute of the vertex
+    /// it was merged into, including the ones the options do not compare.
+    ///
+    /// Nearby vertices are found with a spatial hash, so welding takes time
+    /// roughly proportional to the number of vertices.
+    pub fn weld(&mut self, options: &WeldOptions) -> WeldReport {
+        let len = self.len();
+
+        let mut hash = SpatialHash::new(options.epsilon);
+        let mut indices = Vec::with_capacity(len);
+        let mut unique_ids = vec![0; len];
+        let mut unique = 0;
+        let mut merged = 0;
+        for i in 0..len {
+            let representative = {
+                let mesh = &*self;
+                hash.find(mesh.points()[i], |id| mesh.points()[id], |id| {
+                    let tex_coords_match = match options.tex_coord_epsilon {
+                        Some(epsilon) => {
+                            let (a, b) = (mesh.tex_coords()[id], mesh.tex_coords()[i]);
+                            let difference = [a[0] - b[0], a[1] - b[1]];
+                            (difference[0] * difference[0] + difference[1] * difference[1]).sqrt() <= epsilon
+                        }
+                        None => true,
+                    };
+                    let normals_match = match options.normal_epsilon {
+                        Some(epsilon) => math::length(math::sub(mesh.normals()[id], mesh.normals()[i])) <= epsilon,
+                        None => true,
+                    };
+
+                    tex_coords_match && normals_match
+                })
+            };
+
+            match representative {
+                Some(id) => {
+                    self.points.inner[i] = self.points.inner[id];
+                    self.tex_coords.inner[i] = self.tex_coords.inner[id];
+                    self.normals.inner[i] = self.normals.inner[id];
+                    if let Some(colors) = &mut self.colors {
+                        colors.inner[i] = colors.inner[id];
+                    }
+                    indices.push(unique_ids[id]);
+                    merged += 1;
+                }
+                None => {
+                    hash.insert(self.points()[i], i);
+                    unique_ids[i] = unique;
+                    indices.push(unique);
+                    unique += 1;
+                }
+            }
+        }
+
+        WeldReport { merged, unique, indices }
+    }
+}
+
+
+#[cfg(test)]
+mod weld_tests {
+    use super::{WeldOptions, WeldReport};
+    use crate::ObjMesh;
+
+    fn test_mesh() -> ObjMesh {
+        let points = vec![
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0], [1.0, 1.0, 0.0001], [0.0, 1.0, 0.0],
+        ];
+        let tex_coords = vec![
+            [0.0, 0.0], [1.0, 0.0], [1.0, 1.0],
+            [0.5, 0.0], [1.0, 1.0], [0.0, 1.0],
+        ];
+        let normals = vec![[0.0, 0.0, 1.0]; 6];
+
+        ObjMesh::new(points, tex_coords, normals)
+    }
+
+    #[test]
+    fn test_weld_identical_vertices() {
+        let mut mesh = ObjMesh::new(
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 0.0]], vec![[0.0, 0.0]; 3], vec![[0.0, 0.0, 1.0]; 3]
+        );
+        let report = mesh.weld(&WeldOptions::default());
+        let expected = WeldReport { merged: 1, unique: 2, indices: vec![0, 1, 0] };
+
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn test_weld_with_tolerance() {
+        let mut mesh = test_mesh();
+        let options = WeldOptions { epsilon: 0.001, tex_coord_epsilon: None, normal_epsilon: None };
+        let report = mesh.weld(&options);
+
+        assert_eq!(report.merged, 2);
+        assert_eq!(report.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.points()[4], [1.0, 1.0, 0.0]);
+        assert_eq!(mesh.tex_coords()[3], [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_weld_keeps_uv_seams() {
+        let mut mesh = test_mesh();
+        let options = WeldOptions { epsilon: 0.001, tex_coord_epsilon: Some(1e-6), normal_epsilon: Some(1e-6) };
+        let report = mesh.weld(&options);
+
+        assert_eq!(report.merged, 1);
+        assert_eq!(report.indices, vec![0, 1, 2, 3, 2, 4]);
+        assert_eq!(mesh.tex_coords()[3], [0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_weld_large_mesh_exactly() {
+        // A flat grid of 200 by 200 unit-scale squares, six vertices to a
+        // square. Exact welding must not scan the whole mesh per vertex.
+        let n = 200;
+        let mut mesh = ObjMesh::with_capacity(6 * n * n);
+        for i in 0..n {
+            for j in 0..n {
+                let corner = |di: usize, dj: usize| [(i + di) as f32 / n as f32, (j + dj) as f32 / n as f32, 0.0];
+                mesh.push_triangle([corner(0, 0), corner(1, 0), corner(1, 1)], [[0.0, 0.0]; 3], [[0.0, 0.0, 1.0]; 3]);
+                mesh.push_triangle([corner(0, 0), corner(1, 1), corner(0, 1)], [[0.0, 0.0]; 3], [[0.0, 0.0, 1.0]; 3]);
+            }
+        }
+        let report = mesh.weld(&WeldOptions::default());
+
+        assert_eq!(report.unique, (n + 1) * (n + 1));
+        assert_eq!(report.merged, 6 * n * n - (n + 1) * (n + 1));
+    }
+}
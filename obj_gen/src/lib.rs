@@ -8,6 +8,10 @@ pub fn to_rust_code(mesh: &ObjMesh) -> String {
     synthesize_code(&ir)
 }
 
+/// The panic message of the generated code, should its buffers ever have
+/// mismatched lengths.
+const GENERATED_MESH_MESSAGE: &str = "generated mesh buffers have mismatched lengths";
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum Token {
     SymUse,
@@ -16,10 +20,13 @@ enum Token {
     SymPoints,
     SymTexCoords,
     SymNormals,
+    SymColors,
     SymTypeFloat32,
     SymTypeObjMesh,
     SymTypeVec,
     SymConstructor,
+    SymExpect,
+    SymWithColors,
     SymMacroVec,
     Equals,
     Colon,
@@ -34,6 +41,8 @@ enum Token {
     Comma,
     LParen,
     RParen,
+    Dot,
+    StringLiteral(&'static str),
     Float32(f32),
     ArrayLength(usize),
     Newline,
@@ -210,8 +219,58 @@ fn generate_normals_code(ir: &mut ObjMeshIR, mesh: &ObjMesh, indent: usize) {
     ir.push(Semicolon);
 }
 
-/// Generate the type constructor invocation code.
-fn generate_type_constructor_invocation(ir: &mut ObjMeshIR, indent: usize) {
+/// Generate the vertex color set code for the object mesh.
+fn generate_colors_code(ir: &mut ObjMeshIR, colors: &[[f32; 3]], indent: usize) {
+    use Token::*;
+
+    ir.push(Whitespace(indent));
+    ir.push(SymLet);
+    ir.push(Whitespace(1));
+    ir.push(SymColors);
+    ir.push(Colon);
+    ir.push(Whitespace(1));
+    ir.push(SymTypeVec);
+    ir.push(LessThan);
+
+    ir.push(LBracket);
+    ir.push(SymTypeFloat32); ir.push(Semicolon); ir.push(Whitespace(1)); ir.push(ArrayLength(3));
+    ir.push(RBracket);
+
+    ir.push(GreaterThan);
+    ir.push(Whitespace(1));
+    ir.push(Equals);
+    ir.push(Whitespace(1));
+    ir.push(SymMacroVec);
+    ir.push(LBracket);
+    ir.push(Newline);
+    ir.push(Whitespace(indent));
+    ir.push(Whitespace(indent));
+
+    for color in colors {
+        ir.push(LBracket);
+        ir.push(Float32(color[0]));
+        ir.push(Comma);
+        ir.push(Whitespace(1));
+        ir.push(Float32(color[1]));
+        ir.push(Comma);
+        ir.push(Whitespace(1));
+        ir.push(Float32(color[2]));
+        ir.push(RBracket);
+        ir.push(Comma);
+        ir.push(Whitespace(1));
+    }
+
+    ir.push(Newline);
+    ir.push(Whitespace(indent));
+    ir.push(RBracket);
+    ir.push(Semicolon);
+}
+
+/// Generate the type constructor invocation code. The buffers come from a
+/// mesh that already exists, so they always have matching lengths, and the
+/// generated code expects the fallible constructor to succeed. The same
+/// holds for the vertex colors of a mesh that has them.
+fn generate_type_constructor_invocation(ir: &mut ObjMeshIR, has_colors: bool, indent: usize) {
     use Token::*;
 
     ir.push(Whitespace(indent));
@@ -221,6 +280,24 @@ fn generate_type_constructor_invocation(ir: &mut ObjMeshIR, indent: usize) {
     ir.push(LParen);
     ir.push(SymPoints); ir.push(Comma); ir.push(Whitespace(1)); ir.push(SymTexCoords); ir.push(Comma); ir.push(Whitespace(1)); ir.push(SymNormals);
     ir.push(RParen);
+    ir.push(Dot);
+    ir.push(SymExpect);
+    ir.push(LParen);
+    ir.push(StringLiteral(GENERATED_MESH_MESSAGE));
+    ir.push(RParen);
+
+    if has_colors {
+        ir.push(Dot);
+        ir.push(SymWithColors);
+        ir.push(LParen);
+        ir.push(SymColors);
+        ir.push(RParen);
+        ir.push(Dot);
+        ir.push(SymExpect);
+        ir.push(LParen);
+        ir.push(StringLiteral(GENERATED_MESH_MESSAGE));
+        ir.push(RParen);
+    }
 }
 
 /// Generate the Rust code expression block for constructing the 
@@ -250,10 +327,16 @@ fn generate_code(mesh: &ObjMesh) -> ObjMeshIR {
     // Generate the normal vector set.
     generate_normals_code(&mut ir, mesh, indent);
     ir.push(Newline);
+
+    // Generate the vertex color set, if the mesh has one.
+    if let Some(colors) = mesh.colors() {
+        generate_colors_code(&mut ir, colors, indent);
+        ir.push(Newline);
+    }
     ir.push(Newline);
 
     // Generate the type constructor invocation.
-    generate_type_constructor_invocation(&mut ir, indent);
+    generate_type_constructor_invocation(&mut ir, mesh.colors().is_some(), indent);
     ir.push(Newline);
 
     // End the code block.    
@@ -271,10 +354,13 @@ fn synthesize_token(token: Token) -> String {
         SymPoints => format!("{}", "points"),
         SymTexCoords => format!("{}", "tex_coords"),
         SymNormals => format!("{}", "normals"),
+        SymColors => format!("{}", "colors"),
         SymTypeFloat32 => format!("{}", "f32"),
         SymTypeObjMesh => format!("{}", "ObjMesh"),
         SymTypeVec => format!("{}", "Vec"),
-        SymConstructor => format!("{}", "new"),
+        SymConstructor => format!("{}", "try_new"),
+        SymExpect => format!("{}", "expect"),
+        SymWithColors => format!("{}", "with_colors"),
         SymMacroVec => format!("{}", "vec!"),
         Equals => format!("{}", "="),
         Colon => format!("{}", ":"),
@@ -289,6 +375,8 @@ fn synthesize_token(token: Token) -> String {
         Comma => format!("{}", ","),
         LParen => format!("{}", "("),
         RParen => format!("{}", ")"),
+        Dot => format!("{}", "."),
+        StringLiteral(string) => format!("{:?}", string),
         Float32(number) => format!("{:.*}", 8, number),
         ArrayLength(number) => format!("{}", number),
         Newline => format!("{}", "\n"),
@@ -563,7 +651,7 @@ mod loader_tests {
                 Newline, Whitespace(4),
                 SymTypeObjMesh, DoubleColon, SymConstructor, LParen, 
                     SymPoints, Comma, Whitespace(1), SymTexCoords, Comma, Whitespace(1), SymNormals, 
-                RParen, Newline,
+                RParen, Dot, SymExpect, LParen, StringLiteral(super::GENERATED_MESH_MESSAGE), RParen, Newline,
             RCurlyBrace,
         ]);
 
@@ -604,6 +692,42 @@ mod loader_tests {
         assert_eq!(result.len(), expected.len());
     }
 
+    #[test]
+    fn test_synthesized_code_uses_fallible_constructor() {
+        let test = test();
+        let result = super::to_rust_code(&test.obj_mesh);
+        let expected = concat!(
+            "    ObjMesh::try_new(points, tex_coords, normals)",
+            ".expect(\"generated mesh buffers have mismatched lengths\")\n}",
+        );
+
+        assert!(result.ends_with(expected), "generated code ends with `{}`", &result[result.len() - 120..]);
+    }
+
+    #[test]
+    fn test_synthesized_code_keeps_vertex_colors() {
+        let points = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let mesh = ObjMesh::new(points, vec![[0.0, 0.0]; 3], vec![[0.0, 0.0, 1.0]; 3])
+            .with_colors(vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+            .unwrap();
+        let result = super::to_rust_code(&mesh);
+        let colors = concat!(
+            "    let colors: Vec<[f32; 3]> = vec![\n        ",
+            "[1.00000000, 0.00000000, 0.00000000], ",
+            "[0.00000000, 1.00000000, 0.00000000], ",
+            "[0.00000000, 0.00000000, 1.00000000], \n    ];\n",
+        );
+        let constructor = concat!(
+            "    ObjMesh::try_new(points, tex_coords, normals)",
+            ".expect(\"generated mesh buffers have mismatched lengths\")",
+            ".with_colors(colors)",
+            ".expect(\"generated mesh buffers have mismatched lengths\")\n}",
+        );
+
+        assert!(result.contains(colors), "generated code is `{}`", result);
+        assert!(result.ends_with(constructor), "generated code is `{}`", result);
+    }
+
     #[test]
     fn test_compile_obj_mesh_elementwise() {
         let test = test();